@@ -0,0 +1,174 @@
+//! Accelerator tables, the keyboard shortcuts of menu commands
+
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::ops::{BitOr, BitOrAssign};
+
+use res;
+
+const FVIRTKEY: u16 = 0x01;
+
+/// The keys that have to be held down together with the key of an accelerator
+///
+/// ```rust
+/// use winres::Modifiers;
+///
+/// let modifiers = Modifiers::CTRL | Modifiers::SHIFT;
+/// assert!(modifiers.contains(Modifiers::CTRL));
+/// assert!(!modifiers.contains(Modifiers::ALT));
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct Modifiers {
+    bits: u16,
+}
+
+impl Modifiers {
+    pub const SHIFT: Modifiers = Modifiers { bits: 0x04 };
+    pub const CTRL: Modifiers = Modifiers { bits: 0x08 };
+    pub const ALT: Modifiers = Modifiers { bits: 0x10 };
+
+    /// No modifier, the key alone
+    pub fn empty() -> Modifiers {
+        Modifiers { bits: 0 }
+    }
+
+    /// The `fFlags` bits of the modifiers
+    pub fn bits(self) -> u16 {
+        self.bits
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+/// The modifiers with their names in resource scripts
+const MODIFIER_NAMES: [(Modifiers, &str); 3] = [(Modifiers::CTRL, "CONTROL"),
+                                                (Modifiers::ALT, "ALT"),
+                                                (Modifiers::SHIFT, "SHIFT")];
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers { bits: self.bits | other.bits }
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+}
+
+impl fmt::Debug for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let set: Vec<&str> = MODIFIER_NAMES.iter().filter(|n| self.contains(n.0)).map(|n| n.1).collect();
+        if set.is_empty() {
+            write!(f, "(empty)")
+        } else {
+            write!(f, "{}", set.join(" | "))
+        }
+    }
+}
+
+/// An accelerator table, built like the `ACCELERATORS` statement of a resource script
+///
+/// Every entry maps a virtual key with modifiers to a command id, that is sent with the
+/// `WM_COMMAND` message. Virtual key codes of letters and digits are their uppercase ASCII
+/// codes, the other ones are the `VK_` constants of the Windows SDK.
+///
+/// # Example
+///
+/// ```rust
+/// use winres::{Accelerators, Modifiers};
+///
+/// let mut res = winres::WindowsResource::new();
+/// res.add_accelerators(1, Accelerators::new()
+///     .add(b'O' as u16, Modifiers::CTRL, 101)
+///     .add(b'S' as u16, Modifiers::CTRL | Modifiers::SHIFT, 102)
+///     .add(0x74, Modifiers::empty(), 103)); // VK_F5
+/// # res.write_res_file(std::env::temp_dir().join("accelerators.res")).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Accelerators {
+    entries: Vec<(u16, Modifiers, u16)>,
+}
+
+impl Accelerators {
+    /// Create an empty table
+    pub fn new() -> Accelerators {
+        Accelerators { entries: Vec::new() }
+    }
+
+    /// Add a shortcut for the command `id`
+    pub fn add(&mut self, key: u16, modifiers: Modifiers, id: u16) -> &mut Self {
+        self.entries.push((key, modifiers, id));
+        self
+    }
+
+    /// Check that the table is not empty and the keys are virtual key codes
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.entries.is_empty() {
+            return Err("the accelerator table is empty".to_string());
+        }
+        match self.entries.iter().find(|e| e.0 == 0 || e.0 > 0xFE) {
+            Some(e) => Err(format!("{:#x} is not a virtual key code", e.0)),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the `ACCELERATORS` statement
+    pub(crate) fn write_rc<W: Write>(&self, w: &mut W, id: &res::ResourceId, language: u16) -> io::Result<()> {
+        writeln!(w, "{} ACCELERATORS", id)?;
+        writeln!(w, "LANGUAGE {:#x}, {:#x}", language & 0x3FF, language >> 10)?;
+        writeln!(w, "{{")?;
+        for &(key, modifiers, id) in &self.entries {
+            write!(w, "{:#x}, {}, VIRTKEY", key, id)?;
+            for &(_, name) in MODIFIER_NAMES.iter().filter(|n| modifiers.contains(n.0)) {
+                write!(w, ", {}", name)?;
+            }
+            writeln!(w)?;
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Serialize the `ACCELTABLEENTRY` array
+    pub(crate) fn table(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.entries.len() * 8);
+        for (i, &(key, modifiers, id)) in self.entries.iter().enumerate() {
+            // the last entry is marked
+            let last = if i + 1 == self.entries.len() { 0x80 } else { 0 };
+            res::push_u16(&mut buf, FVIRTKEY | modifiers.bits() | last);
+            res::push_u16(&mut buf, key);
+            res::push_u16(&mut buf, id);
+            res::push_u16(&mut buf, 0); // padding
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accelerator_table() {
+        // 0x4F, 101, VIRTKEY, CONTROL; 0x70, 103, VIRTKEY; 0x2E, 102, VIRTKEY, SHIFT, ALT;
+        // as compiled by llvm-rc
+        const EXPECTED: [u8; 24] = [
+            0x09, 0x00, 0x4f, 0x00, 0x65, 0x00, 0x00, 0x00, 0x01, 0x00, 0x70, 0x00, 0x67, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x2e, 0x00, 0x66, 0x00, 0x00, 0x00,
+        ];
+        let mut accelerators = Accelerators::new();
+        accelerators.add(b'O' as u16, Modifiers::CTRL, 101)
+            .add(0x70, Modifiers::empty(), 103)
+            .add(0x2E, Modifiers::SHIFT | Modifiers::ALT, 102);
+        assert_eq!(&accelerators.table()[..], &EXPECTED[..]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Reader for bitmap (`.bmp`) files
+//!
+//! A bitmap file is a `BITMAPFILEHEADER` followed by a device independent bitmap (DIB):
+//! the info header, the color table and the pixels. The `RT_BITMAP` resource is the DIB
+//! without the file header, and `LoadBitmap()` expects the pixels to follow the color table
+//! directly. Files that leave a gap before the pixels are repacked.
+
+use std::fs;
+use std::io::prelude::*;
+use std::path::Path;
+
+use error::{Error, Result};
+use res;
+
+const FILE_HEADER_SIZE: usize = 14;
+
+/// The sizes of the known info headers, from `BITMAPCOREHEADER` to `BITMAPV5HEADER`
+const INFO_HEADER_SIZES: [usize; 7] = [12, 40, 52, 56, 64, 108, 124];
+
+/// The `bV5CSType` values of a `BITMAPV5HEADER` with a color profile, `'MBED'` and `'LINK'`
+const PROFILE_EMBEDDED: u32 = 0x4D42_4544;
+const PROFILE_LINKED: u32 = 0x4C49_4E4B;
+
+/// A bitmap as stored in the resource
+pub struct Bitmap {
+    /// the packed DIB
+    pub dib: Vec<u8>,
+    /// the offset of the pixels in `dib`
+    pub bits_offset: usize,
+    /// whether the file had to be changed to get `dib`, i.e., the pixels did not follow the color table
+    pub repacked: bool,
+}
+
+impl Bitmap {
+    /// The bitmap as file, with the pixels directly after the color table
+    pub fn to_file(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(FILE_HEADER_SIZE + self.dib.len());
+        buf.extend_from_slice(b"BM");
+        res::push_u32(&mut buf, (FILE_HEADER_SIZE + self.dib.len()) as u32);
+        res::push_u32(&mut buf, 0); // Reserved
+        res::push_u32(&mut buf, (FILE_HEADER_SIZE + self.bits_offset) as u32);
+        buf.extend_from_slice(&self.dib);
+        buf
+    }
+}
+
+/// Read and check a bitmap file
+pub fn read_bitmap<P: AsRef<Path>>(path: P) -> Result<Bitmap> {
+    let mut buf = Vec::new();
+    fs::File::open(path.as_ref())?.read_to_end(&mut buf)?;
+    parse_bitmap(&buf).map_err(|message| {
+        Error::InvalidBitmap {
+            path: path.as_ref().to_path_buf(),
+            message,
+        }
+    })
+}
+
+fn parse_bitmap(buf: &[u8]) -> ::std::result::Result<Bitmap, String> {
+    if buf.len() < FILE_HEADER_SIZE + 4 || &buf[0..2] != b"BM" {
+        return Err("not a bitmap file".to_string());
+    }
+    let file_size = u32_at(buf, 2) as usize;
+    let bits_offset = u32_at(buf, 10) as usize;
+    let header_size = u32_at(buf, 14) as usize;
+    if !INFO_HEADER_SIZES.contains(&header_size) {
+        return Err(format!("the header size {} is unknown", header_size));
+    }
+    if buf.len() < FILE_HEADER_SIZE + header_size || file_size > buf.len() {
+        return Err("the file is truncated".to_string());
+    }
+    let info = &buf[FILE_HEADER_SIZE..];
+    let core = header_size == 12;
+    let (width, height, planes, bit_count) = if core {
+        (u16_at(info, 4) as i64, u16_at(info, 6) as i64, u16_at(info, 8), u16_at(info, 10))
+    } else {
+        (u32_at(info, 4) as i32 as i64, u32_at(info, 8) as i32 as i64, u16_at(info, 12), u16_at(info, 14))
+    };
+    let compression = if core { 0 } else { u32_at(info, 16) };
+    if width <= 0 || height == 0 {
+        return Err(format!("the size {}x{} is invalid", width, height));
+    }
+    if planes != 1 {
+        return Err(format!("it has {} planes instead of 1", planes));
+    }
+    let valid = match compression {
+        // BI_RGB
+        0 => [1, 4, 8, 16, 24, 32].contains(&bit_count),
+        // BI_RLE8 and BI_RLE4, only for bottom-up bitmaps
+        1 => bit_count == 8 && height > 0,
+        2 => bit_count == 4 && height > 0,
+        // BI_BITFIELDS and BI_ALPHABITFIELDS
+        3 | 6 => bit_count == 16 || bit_count == 32,
+        4 | 5 => return Err("JPEG and PNG compressed bitmaps can not be loaded with LoadBitmap()".to_string()),
+        _ => return Err(format!("the compression {} is unknown", compression)),
+    };
+    if !valid {
+        return Err(format!("{} bits per pixel do not work with compression {}", bit_count, compression));
+    }
+
+    // the color masks follow a BITMAPINFOHEADER, the later headers contain them
+    let masks = match (header_size, compression) {
+        (40, 3) => 12,
+        (40, 6) => 16,
+        _ => 0,
+    };
+    let colors_used = if core { 0 } else { u32_at(info, 32) as usize };
+    let colors = match colors_used {
+        0 if bit_count <= 8 => 1 << bit_count,
+        n => n,
+    };
+    let table_end = colors.checked_mul(if core { 3 } else { 4 })
+        .and_then(|table| table.checked_add(FILE_HEADER_SIZE + header_size + masks))
+        .ok_or_else(|| format!("the color table of {} colors is invalid", colors))?;
+    let bits_size = if compression == 1 || compression == 2 {
+        Some(u32_at(info, 20) as usize)
+    } else {
+        // the rows are padded to 4 bytes
+        (width as usize).checked_mul(bit_count as usize)
+            .and_then(|bits| bits.checked_add(31))
+            .and_then(|bits| ((bits & !31) / 8).checked_mul(height.unsigned_abs() as usize))
+    };
+    let bits_size = bits_size.ok_or_else(|| format!("the size {}x{} is invalid", width, height))?;
+    if bits_offset < table_end {
+        return Err("the pixels overlap the color table".to_string());
+    }
+    if bits_offset > buf.len() || buf.len() - bits_offset < bits_size {
+        return Err("the file is truncated".to_string());
+    }
+
+    let repacked = bits_offset != table_end;
+    let mut dib = buf[FILE_HEADER_SIZE..table_end].to_vec();
+    dib.extend_from_slice(&buf[bits_offset..]);
+    if repacked && header_size == 124 {
+        // bV5ProfileData is relative to the info header, a profile after the pixels moves with them
+        let cs_type = u32_at(info, 56);
+        let profile = u32_at(info, 112) as usize;
+        if (cs_type == PROFILE_EMBEDDED || cs_type == PROFILE_LINKED) && u32_at(info, 116) != 0 {
+            if FILE_HEADER_SIZE + profile < bits_offset {
+                return Err("the color profile is in front of the pixels, the gap before them can not be removed".to_string());
+            }
+            let moved = (profile - (bits_offset - table_end)) as u32;
+            dib[112..116].copy_from_slice(&moved.to_le_bytes());
+        }
+    }
+    Ok(Bitmap {
+        dib,
+        bits_offset: table_end - FILE_HEADER_SIZE,
+        repacked,
+    })
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]])
+}
@@ -0,0 +1,312 @@
+//! Writer for COFF object files holding a `.rsrc` section
+//!
+//! This does the same as `cvtres.exe` or `windres -O coff`: the list of resources is
+//! turned into the resource directory tree found in the `.rsrc` section of an executable.
+//! The linker merges this section into the final image, the only thing left to it is to
+//! relocate the addresses of the resource data.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::prelude::*;
+
+use res::{self, Resource, ResourceId};
+
+/// Machine types we can write an object file for
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Machine {
+    X86,
+    X86_64,
+    Aarch64,
+}
+
+impl Machine {
+    /// Get the machine type for a `target_arch` value
+    pub fn from_arch(arch: &str) -> Option<Machine> {
+        match arch {
+            "x86" => Some(Machine::X86),
+            "x86_64" => Some(Machine::X86_64),
+            "aarch64" => Some(Machine::Aarch64),
+            _ => None,
+        }
+    }
+
+    fn id(self) -> u16 {
+        match self {
+            Machine::X86 => 0x014c,
+            Machine::X86_64 => 0x8664,
+            Machine::Aarch64 => 0xaa64,
+        }
+    }
+
+    /// The relocation type for an image relative (RVA) address
+    fn rva_relocation(self) -> u16 {
+        match self {
+            // IMAGE_REL_I386_DIR32NB
+            Machine::X86 => 0x0007,
+            // IMAGE_REL_AMD64_ADDR32NB
+            Machine::X86_64 => 0x0003,
+            // IMAGE_REL_ARM64_ADDR32NB
+            Machine::Aarch64 => 0x0002,
+        }
+    }
+}
+
+const FILE_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const RELOCATION_SIZE: usize = 10;
+const DIRECTORY_SIZE: usize = 16;
+const DIRECTORY_ENTRY_SIZE: usize = 8;
+const DATA_ENTRY_SIZE: usize = 16;
+
+/// Type -> Name -> Language, the three levels of the resource directory
+///
+/// The order of `ResourceId` is the order of the directory entries: names first, then numbers.
+type Tree<'a> = BTreeMap<&'a ResourceId, BTreeMap<&'a ResourceId, BTreeMap<u16, &'a Resource>>>;
+
+/// Write an object file with a single `.rsrc` section
+pub fn write_object<W: Write>(w: &mut W, machine: Machine, resources: &[Resource]) -> io::Result<()> {
+    let mut tree: Tree = BTreeMap::new();
+    for r in resources {
+        tree.entry(&r.res_type)
+            .or_default()
+            .entry(&r.name)
+            .or_default()
+            .insert(r.language, r);
+    }
+
+    // The section starts with all directory tables, breadth first, followed by the data
+    // entries, the strings of named entries and finally the resource data itself.
+    let names: usize = tree.values().map(|n| n.len()).sum();
+    let leafs: usize = tree.values().flat_map(|n| n.values()).map(|l| l.len()).sum();
+    let directories_size = DIRECTORY_SIZE * (1 + tree.len() + names) +
+                           DIRECTORY_ENTRY_SIZE * (tree.len() + names + leafs);
+    let data_entries = directories_size;
+    let strings_start = data_entries + DATA_ENTRY_SIZE * leafs;
+
+    // strings are stored with their length and without terminating zero
+    let mut strings = Vec::new();
+    let mut string_offsets = BTreeMap::new();
+    for id in tree.keys().chain(tree.values().flat_map(|n| n.keys())) {
+        if let ResourceId::Name(ref name) = **id {
+            string_offsets.entry(name.as_str()).or_insert_with(|| {
+                let offset = strings_start + strings.len();
+                let chars: Vec<u16> = name.encode_utf16().collect();
+                res::push_u16(&mut strings, chars.len() as u16);
+                for c in chars {
+                    res::push_u16(&mut strings, c);
+                }
+                offset
+            });
+        }
+    }
+    let pad = res::padding8(strings.len());
+    strings.extend_from_slice(&[0; 7][..pad]);
+    let entry_id = |id: &ResourceId| match *id {
+        ResourceId::Ordinal(n) => n as u32,
+        ResourceId::Name(ref name) => string_offsets[name.as_str()] as u32 | 0x8000_0000,
+    };
+    let mut data_offset = strings_start + strings.len();
+
+    let mut section = Vec::new();
+    let mut relocations = Vec::new();
+    let mut data = Vec::new();
+
+    // the offsets of the next directory table and the next data entry
+    let mut next_table = DIRECTORY_SIZE + DIRECTORY_ENTRY_SIZE * tree.len();
+    let mut next_leaf = data_entries;
+
+    // root table, entries point to the type tables
+    push_directory(&mut section, named(tree.keys()), tree.len());
+    for (res_type, names) in &tree {
+        push_entry(&mut section, entry_id(res_type), next_table as u32 | 0x8000_0000);
+        next_table += DIRECTORY_SIZE + DIRECTORY_ENTRY_SIZE * names.len();
+    }
+    // type tables, entries point to the name tables
+    for names in tree.values() {
+        push_directory(&mut section, named(names.keys()), names.len());
+        for (name, languages) in names {
+            push_entry(&mut section, entry_id(name), next_table as u32 | 0x8000_0000);
+            next_table += DIRECTORY_SIZE + DIRECTORY_ENTRY_SIZE * languages.len();
+        }
+    }
+    // name tables, entries point to the data entries
+    for languages in tree.values().flat_map(|n| n.values()) {
+        push_directory(&mut section, 0, languages.len());
+        for language in languages.keys() {
+            push_entry(&mut section, *language as u32, next_leaf as u32);
+            next_leaf += DATA_ENTRY_SIZE;
+        }
+    }
+    // data entries, the address of the data is relative to the image base and has to be relocated
+    for r in tree.values().flat_map(|n| n.values()).flat_map(|l| l.values()) {
+        data_offset += res::padding8(data_offset);
+        relocations.push(section.len() as u32);
+        res::push_u32(&mut section, data_offset as u32);
+        res::push_u32(&mut section, r.data.len() as u32);
+        res::push_u32(&mut section, 0); // CodePage
+        res::push_u32(&mut section, 0); // Reserved
+        let pad = res::padding8(data.len());
+        data.extend_from_slice(&[0; 7][..pad]);
+        data.extend_from_slice(&r.data);
+        data_offset += r.data.len();
+    }
+    section.extend_from_slice(&strings);
+    section.extend_from_slice(&data);
+    res::align(&mut section);
+
+    let section_start = FILE_HEADER_SIZE + SECTION_HEADER_SIZE;
+    let relocations_start = section_start + section.len();
+    let symbols_start = relocations_start + RELOCATION_SIZE * relocations.len();
+
+    let mut obj = Vec::with_capacity(symbols_start + 40);
+    // file header
+    res::push_u16(&mut obj, machine.id());
+    res::push_u16(&mut obj, 1); // NumberOfSections
+    res::push_u32(&mut obj, 0); // TimeDateStamp
+    res::push_u32(&mut obj, symbols_start as u32);
+    res::push_u32(&mut obj, 2); // NumberOfSymbols, the section symbol and its auxiliary record
+    res::push_u16(&mut obj, 0); // SizeOfOptionalHeader
+    res::push_u16(&mut obj, if machine == Machine::X86 { 0x0100 } else { 0 }); // IMAGE_FILE_32BIT_MACHINE
+
+    // section header
+    obj.extend_from_slice(b".rsrc\0\0\0");
+    res::push_u32(&mut obj, 0); // VirtualSize
+    res::push_u32(&mut obj, 0); // VirtualAddress
+    res::push_u32(&mut obj, section.len() as u32);
+    res::push_u32(&mut obj, section_start as u32);
+    res::push_u32(&mut obj, relocations_start as u32);
+    res::push_u32(&mut obj, 0); // PointerToLinenumbers
+    res::push_u16(&mut obj, relocations.len() as u16);
+    res::push_u16(&mut obj, 0); // NumberOfLinenumbers
+    // IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_ALIGN_4BYTES | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE
+    res::push_u32(&mut obj, 0xC030_0040);
+
+    obj.extend_from_slice(&section);
+
+    for offset in &relocations {
+        res::push_u32(&mut obj, *offset);
+        res::push_u32(&mut obj, 0); // the section symbol
+        res::push_u16(&mut obj, machine.rva_relocation());
+    }
+
+    // section symbol
+    obj.extend_from_slice(b".rsrc\0\0\0");
+    res::push_u32(&mut obj, 0); // Value
+    res::push_u16(&mut obj, 1); // SectionNumber
+    res::push_u16(&mut obj, 0); // Type
+    obj.push(3); // IMAGE_SYM_CLASS_STATIC
+    obj.push(1); // NumberOfAuxSymbols
+    // auxiliary section definition
+    res::push_u32(&mut obj, section.len() as u32);
+    res::push_u16(&mut obj, relocations.len() as u16);
+    res::push_u16(&mut obj, 0); // NumberOfLinenumbers
+    res::push_u32(&mut obj, 0); // CheckSum
+    res::push_u16(&mut obj, 0); // Number
+    obj.extend_from_slice(&[0; 4]); // Selection and padding
+
+    // empty string table
+    res::push_u32(&mut obj, 4);
+
+    w.write_all(&obj)
+}
+
+/// Write a static library (`ar` archive) containing a single object file
+///
+/// The archive has an empty symbol table, the object does not export anything
+/// and the linker has to be told to include it anyway.
+pub fn write_archive<W: Write>(w: &mut W, name: &str, object: &[u8]) -> io::Result<()> {
+    w.write_all(b"!<arch>\n")?;
+    archive_member(w, "/", &0u32.to_be_bytes())?;
+    archive_member(w, &format!("{}/", name), object)
+}
+
+fn archive_member<W: Write>(w: &mut W, name: &str, data: &[u8]) -> io::Result<()> {
+    writeln!(w, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", name, 0, 0, 0, 644, data.len())?;
+    w.write_all(data)?;
+    if data.len() & 1 != 0 {
+        w.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn push_directory(buf: &mut Vec<u8>, named: usize, entries: usize) {
+    res::push_u32(buf, 0); // Characteristics
+    res::push_u32(buf, 0); // TimeDateStamp
+    res::push_u16(buf, 0); // MajorVersion
+    res::push_u16(buf, 0); // MinorVersion
+    res::push_u16(buf, named as u16);
+    res::push_u16(buf, (entries - named) as u16);
+}
+
+/// The number of named ids
+fn named<'a, I: Iterator<Item = &'a &'a ResourceId>>(ids: I) -> usize {
+    ids.filter(|id| matches!(***id, ResourceId::Name(_))).count()
+}
+
+fn push_entry(buf: &mut Vec<u8>, id: u32, offset: u32) {
+    res::push_u32(buf, id);
+    res::push_u32(buf, offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(buf: &[u8], pos: usize) -> u16 {
+        u16::from_le_bytes([buf[pos], buf[pos + 1]])
+    }
+
+    fn u32_at(buf: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]])
+    }
+
+    #[test]
+    fn two_resources() {
+        let resources = [
+            Resource::new(ResourceId::Ordinal(res::RT_RCDATA), ResourceId::from("config"), 0x409, b"abc".to_vec()),
+            Resource::new(ResourceId::Ordinal(res::RT_MANIFEST), ResourceId::Ordinal(1), 0x409, b"<xml/>".to_vec()),
+        ];
+        for &(machine, id, relocation) in &[(Machine::X86, 0x014c, 7), (Machine::X86_64, 0x8664, 3), (Machine::Aarch64, 0xaa64, 2)] {
+            let mut obj = Vec::new();
+            write_object(&mut obj, machine, &resources).unwrap();
+
+            assert_eq!(u16_at(&obj, 0), id);
+            assert_eq!(u16_at(&obj, 2), 1);
+            let section = &obj[60..60 + u32_at(&obj, 36) as usize];
+            assert_eq!(section.len(), 192);
+            assert_eq!(u32_at(&obj, 40), 60);
+
+            // root: the types RT_RCDATA and RT_MANIFEST
+            assert_eq!((u16_at(section, 12), u16_at(section, 14)), (0, 2));
+            assert_eq!((u32_at(section, 16), u32_at(section, 20)), (10, 0x8000_0000 | 32));
+            assert_eq!((u32_at(section, 24), u32_at(section, 28)), (24, 0x8000_0000 | 56));
+            // RT_RCDATA has the name CONFIG, the string is behind the data entries
+            assert_eq!((u16_at(section, 32 + 12), u16_at(section, 32 + 14)), (1, 0));
+            assert_eq!((u32_at(section, 48), u32_at(section, 52)), (0x8000_0000 | 160, 0x8000_0000 | 80));
+            assert_eq!(&section[160..174], b"\x06\0C\0O\0N\0F\0I\0G\0");
+            // RT_MANIFEST has the id 1
+            assert_eq!((u16_at(section, 56 + 12), u16_at(section, 56 + 14)), (0, 1));
+            assert_eq!((u32_at(section, 72), u32_at(section, 76)), (1, 0x8000_0000 | 104));
+            // the languages point to the data entries
+            assert_eq!((u16_at(section, 80 + 12), u16_at(section, 80 + 14)), (0, 1));
+            assert_eq!((u32_at(section, 96), u32_at(section, 100)), (0x409, 128));
+            assert_eq!((u16_at(section, 104 + 12), u16_at(section, 104 + 14)), (0, 1));
+            assert_eq!((u32_at(section, 120), u32_at(section, 124)), (0x409, 144));
+            // the data entries, with the data aligned to 8 bytes
+            assert_eq!((u32_at(section, 128), u32_at(section, 132)), (176, 3));
+            assert_eq!((u32_at(section, 144), u32_at(section, 148)), (184, 6));
+            assert_eq!(&section[176..179], b"abc");
+            assert_eq!(&section[184..190], b"<xml/>");
+
+            // the addresses of the data entries are relocated against the section symbol
+            let relocations = u32_at(&obj, 44) as usize;
+            assert_eq!(relocations, 60 + 192);
+            assert_eq!(u16_at(&obj, 52), 2);
+            for (i, &offset) in [128, 144].iter().enumerate() {
+                let r = relocations + i * RELOCATION_SIZE;
+                assert_eq!((u32_at(&obj, r), u32_at(&obj, r + 4), u16_at(&obj, r + 8)), (offset, 0, relocation));
+            }
+            assert_eq!(u32_at(&obj, 8) as usize, relocations + 2 * RELOCATION_SIZE);
+        }
+    }
+}
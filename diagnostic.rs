@@ -0,0 +1,142 @@
+//! Messages of the resource compilers
+
+use std::fmt;
+
+/// How bad a diagnostic message is
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single message of the resource compiler, preprocessor or archiver
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the file the message refers to, if it names one
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Parse one line of compiler output
+    ///
+    /// The formats of `rc.exe` (`file.rc(12) : error RC2135 : message`) and the GNU tools
+    /// (`windres: file.rc:12: message` or `file.rc:12:5: warning: message`) are understood,
+    /// as well as lines without location that start with `error` or `warning`.
+    /// Anything else yields `None`.
+    ///
+    /// ```rust
+    /// use winres::{Diagnostic, Severity};
+    ///
+    /// let d = Diagnostic::parse(r"C:\src\resource.rc(7) : error RC2135 : file not found: icon.ico").unwrap();
+    /// assert_eq!(d.severity, Severity::Error);
+    /// assert_eq!(d.file.as_ref().map(|f| f.as_str()), Some(r"C:\src\resource.rc"));
+    /// assert_eq!(d.line, Some(7));
+    /// assert_eq!(d.message, "file not found: icon.ico");
+    ///
+    /// let d = Diagnostic::parse("x86_64-w64-mingw32-windres: resource.rc:3: syntax error").unwrap();
+    /// assert_eq!((d.severity, d.line), (Severity::Error, Some(3)));
+    /// ```
+    pub fn parse(line: &str) -> Option<Diagnostic> {
+        let line = line.trim();
+        parse_msvc(line).or_else(|| parse_gnu(line))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.file.as_ref(), self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: ", file, line)?,
+            (Some(file), None) => write!(f, "{}: ", file)?,
+            _ => {}
+        }
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// `file(line) : error CODE : message`
+fn parse_msvc(line: &str) -> Option<Diagnostic> {
+    // the path itself may contain parentheses, e.g., `Program Files (x86)`
+    let (open, number, rest) = line.char_indices().filter(|&(_, c)| c == '(').find_map(|(open, _)| {
+        let close = open + line[open..].find(')')?;
+        let number = line[open + 1..close].parse().ok()?;
+        let rest = line[close + 1..].trim_start().strip_prefix(':')?;
+        Some((open, number, rest.trim_start()))
+    })?;
+    let (severity, rest) = split_severity(rest)?;
+    // skip the message code, e.g., `RC2135`
+    let message = match rest.find(':') {
+        Some(i) => rest[i + 1..].trim(),
+        None => rest.trim(),
+    };
+    Some(Diagnostic {
+        severity,
+        file: Some(line[..open].trim().to_string()),
+        line: Some(number),
+        message: message.to_string(),
+    })
+}
+
+/// `program: file:line:column: severity: message`, most parts are optional
+fn parse_gnu(line: &str) -> Option<Diagnostic> {
+    // look for the first `:<number>:`, the part before is the file name, possibly prefixed with the program
+    let location = line.char_indices().filter(|&(_, c)| c == ':').find_map(|(i, _)| {
+        let digits = line[i + 1..].find(|c: char| !c.is_ascii_digit())?;
+        if digits > 0 && line[i + 1 + digits..].starts_with(':') {
+            Some((i, line[i + 1..i + 1 + digits].parse().ok()?, i + 2 + digits))
+        } else {
+            None
+        }
+    });
+    let (file, number, rest) = match location {
+        Some((end, number, rest)) => {
+            let mut file = &line[..end];
+            // strip `windres: `, but not the drive letter of a windows path
+            if let Some(i) = file.find(": ") {
+                file = &file[i + 2..];
+            }
+            (Some(file.trim().to_string()), Some(number), &line[rest..])
+        }
+        None => (None, None, line),
+    };
+    // skip the column
+    let rest = rest.trim_start();
+    let rest = match rest.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) if i > 0 && rest[i..].starts_with(':') => rest[i + 1..].trim_start(),
+        _ => rest,
+    };
+    let (severity, message) = match split_severity(rest) {
+        Some((severity, message)) => (severity, message.trim_start_matches(':').trim()),
+        // windres reports syntax errors without a severity
+        None if file.is_some() => (Severity::Error, rest.trim()),
+        None => {
+            // `windres: warning: message`
+            let i = rest.find(": ")?;
+            let (severity, message) = split_severity(&rest[i + 2..])?;
+            (severity, message.strip_prefix(':')?.trim())
+        }
+    };
+    Some(Diagnostic {
+        severity,
+        file,
+        line: number,
+        message: message.to_string(),
+    })
+}
+
+fn split_severity(s: &str) -> Option<(Severity, &str)> {
+    for &(prefix, severity) in &[("fatal error", Severity::Error), ("error", Severity::Error), ("warning", Severity::Warning)] {
+        if s.len() >= prefix.len() && s.is_char_boundary(prefix.len()) && s[..prefix.len()].eq_ignore_ascii_case(prefix) {
+            let rest = &s[prefix.len()..];
+            if rest.is_empty() || rest.starts_with(':') || rest.starts_with(' ') {
+                return Some((severity, rest.trim_start()));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,427 @@
+//! Dialog templates
+//!
+//! A dialog is written as `DIALOGEX` statement to the resource script, or as
+//! `DLGTEMPLATEEX` structure followed by one `DLGITEMTEMPLATEEX` per control.
+//!
+//! Styles are the numeric values of the `WS_`, `DS_` and control specific constants
+//! of the Windows SDK, e.g., `BS_AUTO3STATE` or `ES_PASSWORD`.
+
+use std::io;
+use std::io::prelude::*;
+
+use res;
+use rc_string;
+
+const WS_POPUP: u32 = 0x8000_0000;
+const WS_CHILD: u32 = 0x4000_0000;
+const WS_VISIBLE: u32 = 0x1000_0000;
+const WS_CAPTION: u32 = 0x00C0_0000;
+const WS_BORDER: u32 = 0x0080_0000;
+const WS_VSCROLL: u32 = 0x0020_0000;
+const WS_SYSMENU: u32 = 0x0008_0000;
+const WS_GROUP: u32 = 0x0002_0000;
+const WS_TABSTOP: u32 = 0x0001_0000;
+const DS_SETFONT: u32 = 0x40;
+const DS_MODALFRAME: u32 = 0x80;
+const DS_FIXEDSYS: u32 = 0x08;
+
+/// A dialog box, built like the `DIALOGEX` statement of a resource script
+///
+/// The position and size are in dialog units, which depend on the font. Without further
+/// settings, the dialog is a modal popup with caption and system menu, using the
+/// `MS Shell Dlg` font in 8 points like the dialogs of Windows itself.
+///
+/// # Example
+///
+/// ```rust
+/// use winres::{Control, Dialog};
+///
+/// let mut res = winres::WindowsResource::new();
+/// res.add_dialog(101, Dialog::new(0, 0, 186, 62)
+///     .caption("Log in")
+///     .add_control(Control::text("&Password:", -1, 7, 9, 50, 8))
+///     .add_control(Control::edit(1001, 60, 7, 119, 14).style(0x0020)) // ES_PASSWORD
+///     .add_control(Control::default_push_button("OK", 1, 75, 41, 50, 14))
+///     .add_control(Control::push_button("Cancel", 2, 129, 41, 50, 14)));
+/// # res.write_res_file(std::env::temp_dir().join("dialog.res")).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Dialog {
+    x: i16,
+    y: i16,
+    width: i16,
+    height: i16,
+    caption: String,
+    typeface: String,
+    point_size: u16,
+    weight: u16,
+    italic: bool,
+    style: u32,
+    ex_style: u32,
+    controls: Vec<Control>,
+}
+
+impl Dialog {
+    /// Create a dialog without controls
+    pub fn new(x: i16, y: i16, width: i16, height: i16) -> Dialog {
+        Dialog {
+            x,
+            y,
+            width,
+            height,
+            caption: String::new(),
+            typeface: "MS Shell Dlg".to_string(),
+            point_size: 8,
+            weight: 0,
+            italic: false,
+            // DS_SHELLFONT is DS_SETFONT | DS_FIXEDSYS
+            style: WS_POPUP | WS_CAPTION | WS_SYSMENU | DS_MODALFRAME | DS_SETFONT | DS_FIXEDSYS,
+            ex_style: 0,
+            controls: Vec::new(),
+        }
+    }
+
+    /// Set the text of the title bar
+    pub fn caption(&mut self, caption: &str) -> &mut Self {
+        self.caption = caption.to_string();
+        self
+    }
+
+    /// Set the font of the dialog and its controls
+    pub fn font(&mut self, typeface: &str, point_size: u16) -> &mut Self {
+        self.typeface = typeface.to_string();
+        self.point_size = point_size;
+        self
+    }
+
+    /// Set the weight of the font, e.g., 700 for bold, the default 0 is normal
+    pub fn font_weight(&mut self, weight: u16) -> &mut Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Use the italic variant of the font
+    pub fn font_italic(&mut self, italic: bool) -> &mut Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Add window and dialog styles, e.g., `DS_CENTER` (`0x0800`)
+    pub fn style(&mut self, style: u32) -> &mut Self {
+        self.style |= style;
+        self
+    }
+
+    /// Remove window and dialog styles, e.g., `WS_SYSMENU` (`0x00080000`)
+    pub fn remove_style(&mut self, style: u32) -> &mut Self {
+        self.style &= !style;
+        self
+    }
+
+    /// Add extended window styles, e.g., `WS_EX_TOOLWINDOW` (`0x80`)
+    pub fn ex_style(&mut self, ex_style: u32) -> &mut Self {
+        self.ex_style |= ex_style;
+        self
+    }
+
+    /// Add a control, the order of the controls is the tab order
+    pub fn add_control(&mut self, control: Control) -> &mut Self {
+        self.controls.push(control);
+        self
+    }
+
+    /// The style as the resource compilers write it
+    ///
+    /// They add `WS_CAPTION` for a caption and `DS_SETFONT` for the font, which is always set.
+    fn full_style(&self) -> u32 {
+        let caption = if self.caption.is_empty() { 0 } else { WS_CAPTION };
+        self.style | caption | DS_SETFONT
+    }
+
+    /// Check that the dialog can be written
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.controls.len() > 0xFFFF {
+            return Err(format!("it has {} controls, at most 65535 are possible", self.controls.len()));
+        }
+        if self.controls.iter().any(|c| c.class.name().is_empty()) {
+            return Err("a control has no window class".to_string());
+        }
+        Ok(())
+    }
+
+    /// Write the `DIALOGEX` statement
+    pub(crate) fn write_rc<W: Write>(&self, w: &mut W, id: &res::ResourceId, language: u16) -> io::Result<()> {
+        writeln!(w, "{} DIALOGEX {}, {}, {}, {}", id, self.x, self.y, self.width, self.height)?;
+        writeln!(w, "STYLE {:#x}", self.full_style())?;
+        writeln!(w, "EXSTYLE {:#x}", self.ex_style)?;
+        if !self.caption.is_empty() {
+            writeln!(w, "CAPTION \"{}\"", rc_string(&self.caption))?;
+        }
+        writeln!(w, "LANGUAGE {:#x}, {:#x}", language & 0x3FF, language >> 10)?;
+        writeln!(w,
+                 "FONT {}, \"{}\", {}, {}, 0x1",
+                 self.point_size,
+                 rc_string(&self.typeface),
+                 self.weight,
+                 self.italic as u8)?;
+        writeln!(w, "{{")?;
+        for c in &self.controls {
+            write!(w, "CONTROL \"{}\", {}, \"{}\", {:#x}", rc_string(&c.text), c.id, c.class.name(), c.style)?;
+            // the resource compilers add WS_CHILD and WS_VISIBLE, unless told otherwise
+            let removed = !c.style & (WS_CHILD | WS_VISIBLE);
+            if removed != 0 {
+                write!(w, " | NOT {:#x}", removed)?;
+            }
+            writeln!(w, ", {}, {}, {}, {}, {:#x}", c.x, c.y, c.width, c.height, c.ex_style)?;
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Serialize the `DLGTEMPLATEEX` structure with its controls
+    pub(crate) fn template(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        res::push_u16(&mut buf, 1); // dlgVer
+        res::push_u16(&mut buf, 0xFFFF); // signature
+        res::push_u32(&mut buf, 0); // helpID
+        res::push_u32(&mut buf, self.ex_style);
+        res::push_u32(&mut buf, self.full_style());
+        res::push_u16(&mut buf, self.controls.len() as u16);
+        push_rect(&mut buf, self.x, self.y, self.width, self.height);
+        res::push_u16(&mut buf, 0); // no menu
+        res::push_u16(&mut buf, 0); // the default dialog class
+        res::push_wstr(&mut buf, &self.caption);
+        res::push_u16(&mut buf, self.point_size);
+        res::push_u16(&mut buf, self.weight);
+        buf.push(self.italic as u8);
+        buf.push(1); // DEFAULT_CHARSET
+        res::push_wstr(&mut buf, &self.typeface);
+        for c in &self.controls {
+            res::align(&mut buf);
+            res::push_u32(&mut buf, 0); // helpID
+            res::push_u32(&mut buf, c.ex_style);
+            res::push_u32(&mut buf, c.style);
+            push_rect(&mut buf, c.x, c.y, c.width, c.height);
+            res::push_u32(&mut buf, c.id as u32);
+            match c.class.ordinal() {
+                Some(n) => {
+                    res::push_u16(&mut buf, 0xFFFF);
+                    res::push_u16(&mut buf, n);
+                }
+                None => res::push_wstr(&mut buf, c.class.name()),
+            }
+            res::push_wstr(&mut buf, &c.text);
+            res::push_u16(&mut buf, 0); // no creation data
+        }
+        buf
+    }
+}
+
+/// A control of a [`Dialog`]
+///
+/// Every kind of control starts with the default style of the matching resource script
+/// statement, together with `WS_CHILD` and `WS_VISIBLE`. The position and size are
+/// in dialog units.
+///
+/// [`Dialog`]: struct.Dialog.html
+#[derive(Clone, Debug)]
+pub struct Control {
+    class: ControlClass,
+    text: String,
+    id: i32,
+    x: i16,
+    y: i16,
+    width: i16,
+    height: i16,
+    style: u32,
+    ex_style: u32,
+}
+
+impl Control {
+    fn new(class: ControlClass, text: &str, id: i32, style: u32, rect: (i16, i16, i16, i16)) -> Control {
+        Control {
+            class,
+            text: text.to_string(),
+            id,
+            x: rect.0,
+            y: rect.1,
+            width: rect.2,
+            height: rect.3,
+            style: WS_CHILD | WS_VISIBLE | style,
+            ex_style: 0,
+        }
+    }
+
+    /// A push button (`PUSHBUTTON`)
+    pub fn push_button(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        Control::new(ControlClass::Button, text, id, WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// The push button that is pressed with the enter key (`DEFPUSHBUTTON`)
+    pub fn default_push_button(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        // BS_DEFPUSHBUTTON
+        Control::new(ControlClass::Button, text, id, 0x01 | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A check box that toggles when clicked (`AUTOCHECKBOX`)
+    pub fn check_box(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        // BS_AUTOCHECKBOX
+        Control::new(ControlClass::Button, text, id, 0x03 | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A radio button that is selected when clicked (`AUTORADIOBUTTON`)
+    ///
+    /// A group of radio buttons starts with a button with the `WS_GROUP` style.
+    pub fn radio_button(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        // BS_AUTORADIOBUTTON
+        Control::new(ControlClass::Button, text, id, 0x09 | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A frame with a title around other controls (`GROUPBOX`)
+    pub fn group_box(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        // BS_GROUPBOX
+        Control::new(ControlClass::Button, text, id, 0x07, (x, y, width, height))
+    }
+
+    /// Left aligned static text (`LTEXT`), the id of static controls is usually -1
+    ///
+    /// Add `SS_CENTER` (`0x01`) or `SS_RIGHT` (`0x02`) to align it otherwise.
+    pub fn text(text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        Control::new(ControlClass::Static, text, id, WS_GROUP, (x, y, width, height))
+    }
+
+    /// A single line text field (`EDITTEXT`)
+    ///
+    /// Add `ES_MULTILINE` (`0x04`) for more lines or `ES_PASSWORD` (`0x20`) to hide the text.
+    pub fn edit(id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        Control::new(ControlClass::Edit, "", id, WS_BORDER | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A drop-down list (`COMBOBOX` with `CBS_DROPDOWNLIST`)
+    ///
+    /// The height includes the opened list.
+    pub fn combo_box(id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        Control::new(ControlClass::ComboBox, "", id, 0x03 | WS_VSCROLL | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A list box (`LISTBOX`)
+    pub fn list_box(id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        // LBS_NOTIFY
+        Control::new(ControlClass::ListBox, "", id, 0x01 | WS_BORDER | WS_VSCROLL | WS_TABSTOP, (x, y, width, height))
+    }
+
+    /// A control of any window class (`CONTROL`), e.g., `"SysListView32"`
+    ///
+    /// Only `WS_CHILD` and `WS_VISIBLE` are set, the class specific styles have to be added.
+    pub fn custom(class: &str, text: &str, id: i32, x: i16, y: i16, width: i16, height: i16) -> Control {
+        Control::new(ControlClass::from_name(class), text, id, 0, (x, y, width, height))
+    }
+
+    /// Add window and control styles
+    pub fn style(mut self, style: u32) -> Control {
+        self.style |= style;
+        self
+    }
+
+    /// Remove window and control styles, e.g., `WS_TABSTOP` (`0x00010000`)
+    pub fn remove_style(mut self, style: u32) -> Control {
+        self.style &= !style;
+        self
+    }
+
+    /// Add extended window styles, e.g., `WS_EX_CLIENTEDGE` (`0x0200`)
+    pub fn ex_style(mut self, ex_style: u32) -> Control {
+        self.ex_style |= ex_style;
+        self
+    }
+}
+
+/// The window class of a control
+///
+/// The classes predefined by Windows are stored as numbers.
+#[derive(Clone, Debug)]
+enum ControlClass {
+    Button,
+    Edit,
+    Static,
+    ListBox,
+    ScrollBar,
+    ComboBox,
+    Custom(String),
+}
+
+impl ControlClass {
+    fn from_name(name: &str) -> ControlClass {
+        match name.to_lowercase().as_str() {
+            "button" => ControlClass::Button,
+            "edit" => ControlClass::Edit,
+            "static" => ControlClass::Static,
+            "listbox" => ControlClass::ListBox,
+            "scrollbar" => ControlClass::ScrollBar,
+            "combobox" => ControlClass::ComboBox,
+            _ => ControlClass::Custom(name.to_string()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match *self {
+            ControlClass::Button => "Button",
+            ControlClass::Edit => "Edit",
+            ControlClass::Static => "Static",
+            ControlClass::ListBox => "ListBox",
+            ControlClass::ScrollBar => "ScrollBar",
+            ControlClass::ComboBox => "ComboBox",
+            ControlClass::Custom(ref name) => name,
+        }
+    }
+
+    fn ordinal(&self) -> Option<u16> {
+        match *self {
+            ControlClass::Button => Some(0x80),
+            ControlClass::Edit => Some(0x81),
+            ControlClass::Static => Some(0x82),
+            ControlClass::ListBox => Some(0x83),
+            ControlClass::ScrollBar => Some(0x84),
+            ControlClass::ComboBox => Some(0x85),
+            ControlClass::Custom(_) => None,
+        }
+    }
+}
+
+fn push_rect(buf: &mut Vec<u8>, x: i16, y: i16, width: i16, height: i16) {
+    for &v in &[x, y, width, height] {
+        res::push_u16(buf, v as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dialog_template() {
+        // DIALOGEX 0, 0, 186, 62 with the default style, a caption and the shell font, and
+        // LTEXT "&Password:", -1, EDITTEXT 1001 with ES_PASSWORD and DEFPUSHBUTTON "OK", 1,
+        // as compiled by llvm-rc
+        const EXPECTED: [u8; 196] = [
+            0x01, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc8, 0x00, 0xc8, 0x80,
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0xba, 0x00, 0x3e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x4c, 0x00,
+            0x6f, 0x00, 0x67, 0x00, 0x20, 0x00, 0x69, 0x00, 0x6e, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0x4d, 0x00, 0x53, 0x00, 0x20, 0x00, 0x53, 0x00, 0x68, 0x00, 0x65, 0x00, 0x6c, 0x00,
+            0x6c, 0x00, 0x20, 0x00, 0x44, 0x00, 0x6c, 0x00, 0x67, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x50, 0x07, 0x00, 0x09, 0x00, 0x32, 0x00, 0x08, 0x00,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x82, 0x00, 0x26, 0x00, 0x50, 0x00, 0x61, 0x00, 0x73, 0x00,
+            0x73, 0x00, 0x77, 0x00, 0x6f, 0x00, 0x72, 0x00, 0x64, 0x00, 0x3a, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x81, 0x50, 0x3c, 0x00, 0x07, 0x00,
+            0x77, 0x00, 0x0e, 0x00, 0xe9, 0x03, 0x00, 0x00, 0xff, 0xff, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x50, 0x4b, 0x00, 0x29, 0x00,
+            0x32, 0x00, 0x0e, 0x00, 0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0x80, 0x00, 0x4f, 0x00, 0x4b, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut dialog = Dialog::new(0, 0, 186, 62);
+        dialog.caption("Log in")
+            .add_control(Control::text("&Password:", -1, 7, 9, 50, 8))
+            .add_control(Control::edit(1001, 60, 7, 119, 14).style(0x0020))
+            .add_control(Control::default_push_button("OK", 1, 75, 41, 50, 14));
+        assert_eq!(&dialog.template()[..], &EXPECTED[..]);
+    }
+}
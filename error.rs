@@ -0,0 +1,130 @@
+//! Error type of this crate
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::result;
+
+use diagnostic::Diagnostic;
+
+/// All the things that can go wrong while building a resource
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file, or starting a program failed
+    Io(io::Error),
+    /// An environment variable usually set by cargo is missing,
+    /// i.e., we are not run from a build script
+    MissingEnvironment(String),
+    /// The target or the requested combination of settings is not supported
+    Unsupported(String),
+    /// A program of the toolkit could not be found
+    ToolkitNotFound(String),
+    /// A program of the toolkit, e.g., the resource compiler, failed
+    Compiler {
+        /// the program that was run
+        program: String,
+        /// its exit code, `None` if it was terminated by a signal
+        code: Option<i32>,
+        /// everything the program printed to stdout and stderr
+        output: String,
+        /// the messages we could make sense of
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The icon or cursor file can not be used
+    InvalidIcon {
+        path: PathBuf,
+        message: String,
+    },
+    /// The bitmap file can not be used
+    InvalidBitmap {
+        path: PathBuf,
+        message: String,
+    },
+    /// The message text file (`.mc`) can not be read
+    InvalidMessageFile {
+        path: PathBuf,
+        /// the line number, starting at 1
+        line: usize,
+        message: String,
+    },
+    /// A resource built in code, e.g., a dialog, can not be written
+    InvalidResource(String),
+    /// The manifest can not be used
+    InvalidManifest(String),
+    /// `Cargo.toml` or its `package.metadata.winres` section is malformed
+    Metadata(String),
+    /// A version number can not be represented in the version info
+    InvalidVersion(String),
+    /// The fields of the version info do not fit together
+    InvalidVersionInfo(String),
+}
+
+/// Result type of this crate
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::MissingEnvironment(ref var) => {
+                write!(f, "Environment variable {} is not set, winres has to be used from a build script", var)
+            }
+            Error::Unsupported(ref msg) => write!(f, "{}", msg),
+            Error::ToolkitNotFound(ref msg) => write!(f, "{}", msg),
+            Error::Compiler { ref program, code, ref output, ref diagnostics } => {
+                match code {
+                    Some(code) => write!(f, "{} failed with exit code {}", program, code)?,
+                    None => write!(f, "{} was terminated", program)?,
+                }
+                if diagnostics.is_empty() {
+                    if !output.trim().is_empty() {
+                        write!(f, "\n{}", output.trim_end())?;
+                    }
+                } else {
+                    for d in diagnostics {
+                        write!(f, "\n{}", d)?;
+                    }
+                }
+                Ok(())
+            }
+            Error::InvalidIcon { ref path, ref message } => write!(f, "Invalid icon {}: {}", path.display(), message),
+            Error::InvalidBitmap { ref path, ref message } => write!(f, "Invalid bitmap {}: {}", path.display(), message),
+            Error::InvalidMessageFile { ref path, line, ref message } => {
+                write!(f, "Invalid message file {}:{}: {}", path.display(), line, message)
+            }
+            Error::InvalidResource(ref msg) => write!(f, "Invalid resource: {}", msg),
+            Error::InvalidManifest(ref msg) => write!(f, "Invalid manifest: {}", msg),
+            Error::Metadata(ref msg) => write!(f, "Invalid metadata in Cargo.toml: {}", msg),
+            Error::InvalidVersion(ref msg) => write!(f, "Invalid version: {}", msg),
+            Error::InvalidVersionInfo(ref msg) => write!(f, "Invalid version info: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+/// Allows the use of `?` in functions returning `io::Result`
+// `io::Error::other` needs Rust 1.74
+#[allow(clippy::io_other_error)]
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::Other, e),
+        }
+    }
+}
@@ -1,22 +1,18 @@
-#[cfg(target_os = "windows")]
 extern crate winres;
-#[cfg(target_os = "windows")]
-extern crate winapi;
 
-#[cfg(target_os = "windows")]
 fn main() {
     use std::io::Write;
+    // the build script runs on the host, the target is only known from cargo's environment
+    if std::env::var("CARGO_CFG_TARGET_OS").unwrap() != "windows" {
+        return;
+    }
     // only build the resource for release builds
     // as calling rc.exe might be slow
     if std::env::var("PROFILE").unwrap() == "release" {
         let mut res = winres::WindowsResource::new();
         res.set_icon("icon.ico")
-           .set_language(
-               winapi::winnt::MAKELANGID(
-                   winapi::winnt::LANG_ENGLISH,
-                   winapi::winnt::SUBLANG_ENGLISH_US
-                )
-            )
+           // MAKELANGID(LANG_ENGLISH, SUBLANG_ENGLISH_US), winapi is only available on Windows hosts
+           .set_language(0x0409)
            .set_manifest_file("manifest.xml");
         match res.compile() {
             Err(e) => {
@@ -27,7 +23,3 @@ fn main() {
         }
     }
 }
-
-#[cfg(not(target_os = "windows"))]
-fn main() {
-}
@@ -0,0 +1,346 @@
+//! Reader and writer for icon (`.ico`) and cursor (`.cur`) files
+//!
+//! An icon file is a small directory followed by the images. The resource format
+//! stores each image as a separate `RT_ICON` resource and the directory as a
+//! `RT_GROUP_ICON` resource, that references the images by their resource id.
+//! Cursors are the same, except that each image has a hotspot, the pixel that
+//! points at the screen position.
+//!
+//! Animated cursors and icons (`.ani`) are RIFF files, they are stored unchanged.
+
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use error::{Error, Result};
+use image::Image;
+use res::{self, Resource, ResourceId, RT_CURSOR, RT_GROUP_CURSOR, RT_GROUP_ICON, RT_ICON};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The two kinds of files that share the icon file format
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum IconKind {
+    Icon,
+    Cursor,
+}
+
+impl IconKind {
+    /// The type field of the file header
+    fn id(self) -> u16 {
+        match self {
+            IconKind::Icon => 1,
+            IconKind::Cursor => 2,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            IconKind::Icon => "icon",
+            IconKind::Cursor => "cursor",
+        }
+    }
+
+    fn file(self) -> &'static str {
+        match self {
+            IconKind::Icon => "an icon file",
+            IconKind::Cursor => "a cursor file",
+        }
+    }
+}
+
+/// A single image of an icon or cursor file
+pub struct IconImage {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    /// for cursors the horizontal position of the hotspot
+    pub planes: u16,
+    /// for cursors the vertical position of the hotspot
+    pub bit_count: u16,
+    pub data: Vec<u8>,
+    /// what the image data says about itself
+    pub format: IconFormat,
+}
+
+/// Size and color depth of an image in an icon file
+///
+/// The values are read from the image itself, not from the icon directory.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct IconFormat {
+    pub width: u32,
+    pub height: u32,
+    /// bits per pixel
+    pub bit_count: u16,
+    /// whether the image is stored PNG compressed, otherwise it is a bitmap
+    pub png: bool,
+}
+
+/// Get the size and color depth of all images in an icon file
+///
+/// This fails if the file is not a valid icon.
+///
+/// ```rust,no_run
+/// # fn test_main() -> winres::Result<()> {
+/// for image in winres::icon_formats("app.ico")? {
+///     println!("{}x{}, {} bits per pixel", image.width, image.height, image.bit_count);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn icon_formats<P: AsRef<Path>>(path: P) -> Result<Vec<IconFormat>> {
+    Ok(read_icon(path)?.iter().map(|image| image.format).collect())
+}
+
+/// Read all images from an icon file
+pub fn read_icon<P: AsRef<Path>>(path: P) -> Result<Vec<IconImage>> {
+    read(path, IconKind::Icon)
+}
+
+/// Read all images from a cursor file
+pub fn read_cursor<P: AsRef<Path>>(path: P) -> Result<Vec<IconImage>> {
+    read(path, IconKind::Cursor)
+}
+
+/// Read an animated cursor or icon, only the RIFF header is checked
+pub fn read_animated<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    fs::File::open(path.as_ref())?.read_to_end(&mut buf)?;
+    let message = if buf.len() < 12 || &buf[0..4] != b"RIFF" || &buf[8..12] != b"ACON" {
+        "not an animated cursor or icon"
+    } else if u32_at(&buf, 4) as usize + 8 > buf.len() {
+        "the file is truncated"
+    } else {
+        return Ok(buf);
+    };
+    Err(Error::InvalidIcon {
+        path: path.as_ref().to_path_buf(),
+        message: message.to_string(),
+    })
+}
+
+fn read<P: AsRef<Path>>(path: P, kind: IconKind) -> Result<Vec<IconImage>> {
+    let mut buf = Vec::new();
+    fs::File::open(path.as_ref())?.read_to_end(&mut buf)?;
+    parse_icon(&buf, kind).map_err(|message| {
+        Error::InvalidIcon {
+            path: path.as_ref().to_path_buf(),
+            message,
+        }
+    })
+}
+
+/// Find common problems of an icon, that still work, but do not look good
+pub fn icon_warnings(images: &[IconImage]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    match images.iter().find(|i| i.format.width == 256 && i.format.height == 256) {
+        None => warnings.push("there is no 256x256 image, large icons will be scaled up and look blurry".to_string()),
+        Some(i) if !i.format.png => {
+            warnings.push("the 256x256 image is not PNG compressed, this makes the executable larger than necessary".to_string())
+        }
+        Some(_) => {}
+    }
+    for image in images {
+        if image.format.width != dimension(image.width) || image.format.height != dimension(image.height) {
+            warnings.push(format!("the icon directory says {}x{}, but the image is {}x{}",
+                                  dimension(image.width),
+                                  dimension(image.height),
+                                  image.format.width,
+                                  image.format.height));
+        }
+    }
+    warnings
+}
+
+/// In the icon directory, 0 stands for 256
+fn dimension(v: u8) -> u32 {
+    if v == 0 { 256 } else { v as u32 }
+}
+
+fn parse_icon(buf: &[u8], kind: IconKind) -> ::std::result::Result<Vec<IconImage>, String> {
+    let other = if kind == IconKind::Icon { IconKind::Cursor } else { IconKind::Icon };
+    if buf.starts_with(PNG_SIGNATURE) {
+        return Err(format!("this is a PNG image, not {}", kind.file()));
+    }
+    if buf.starts_with(b"BM") {
+        return Err(format!("this is a bitmap, not {}", kind.file()));
+    }
+    if buf.starts_with(b"RIFF") {
+        return Err(format!("this is an animated {}, not {}", kind.name(), kind.file()));
+    }
+    if buf.len() >= 6 && u16_at(buf, 0) == 0 && u16_at(buf, 2) == other.id() {
+        return Err(format!("this is {}, not {}", other.file(), kind.file()));
+    }
+    if buf.len() < 6 || u16_at(buf, 0) != 0 || u16_at(buf, 2) != kind.id() {
+        return Err(format!("not {}", kind.file()));
+    }
+    let count = u16_at(buf, 4) as usize;
+    if count == 0 {
+        return Err(format!("the {} contains no images", kind.name()));
+    }
+    let mut images = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        if buf.len() < entry + 16 {
+            return Err(format!("the {} directory is truncated", kind.name()));
+        }
+        let size = u32_at(buf, entry + 8) as usize;
+        let offset = u32_at(buf, entry + 12) as usize;
+        if !matches!(offset.checked_add(size), Some(end) if end <= buf.len()) {
+            return Err(format!("image {} exceeds the file size", i + 1));
+        }
+        let format = image_format(&buf[offset..offset + size])
+            .ok_or_else(|| format!("image {} is neither a PNG nor a bitmap", i + 1))?;
+        images.push(IconImage {
+            width: buf[entry],
+            height: buf[entry + 1],
+            color_count: buf[entry + 2],
+            planes: u16_at(buf, entry + 4),
+            bit_count: u16_at(buf, entry + 6),
+            data: buf[offset..offset + size].to_vec(),
+            format,
+        });
+    }
+    Ok(images)
+}
+
+/// Read size and color depth from the PNG header or the `BITMAPINFOHEADER`
+fn image_format(data: &[u8]) -> Option<IconFormat> {
+    if data.starts_with(PNG_SIGNATURE) {
+        // the IHDR chunk always comes first
+        if data.len() < 26 || &data[12..16] != b"IHDR" {
+            return None;
+        }
+        let channels = match data[25] {
+            0 | 3 => 1, // grayscale, palette
+            2 => 3, // RGB
+            4 => 2, // grayscale with alpha
+            6 => 4, // RGBA
+            _ => return None,
+        };
+        Some(IconFormat {
+            width: u32::from_be_bytes([data[16], data[17], data[18], data[19]]),
+            height: u32::from_be_bytes([data[20], data[21], data[22], data[23]]),
+            bit_count: data[24] as u16 * channels,
+            png: true,
+        })
+    } else if data.len() >= 40 && u32_at(data, 0) >= 40 {
+        Some(IconFormat {
+            width: u32_at(data, 4),
+            // the height includes the transparency mask
+            height: u32_at(data, 8) / 2,
+            bit_count: u16_at(data, 14),
+            png: false,
+        })
+    } else {
+        None
+    }
+}
+
+/// Write an icon file with the images in the given order
+///
+/// Images of 256 pixels are stored PNG compressed, smaller ones as bitmaps, which every
+/// Windows version understands.
+pub fn write_icon<W: Write>(w: &mut W, images: &[Image]) -> io::Result<()> {
+    let data: Vec<Vec<u8>> = images.iter()
+        .map(|i| if i.width >= 256 { i.to_png() } else { i.to_icon_bitmap() })
+        .collect();
+    let mut buf = Vec::new();
+    res::push_u16(&mut buf, 0);
+    res::push_u16(&mut buf, 1);
+    res::push_u16(&mut buf, images.len() as u16);
+    let mut offset = 6 + 16 * images.len();
+    for (image, data) in images.iter().zip(&data) {
+        // 256 is stored as 0
+        buf.push(image.width as u8);
+        buf.push(image.height as u8);
+        buf.push(0); // ColorCount
+        buf.push(0); // Reserved
+        res::push_u16(&mut buf, 1); // Planes
+        res::push_u16(&mut buf, 32); // BitCount
+        res::push_u32(&mut buf, data.len() as u32);
+        res::push_u32(&mut buf, offset as u32);
+        offset += data.len();
+    }
+    for data in &data {
+        buf.extend_from_slice(data);
+    }
+    w.write_all(&buf)
+}
+
+/// Convert an icon into its `RT_ICON` resources and the `RT_GROUP_ICON` that ties them together
+///
+/// The images get consecutive ids starting at `first_id`.
+pub fn icon_resources(name: ResourceId, images: Vec<IconImage>, first_id: u16, language: u16) -> Vec<Resource> {
+    let mut group = Vec::new();
+    res::push_u16(&mut group, 0);
+    res::push_u16(&mut group, 1);
+    res::push_u16(&mut group, images.len() as u16);
+    let mut resources = Vec::with_capacity(images.len() + 1);
+    for (i, image) in images.into_iter().enumerate() {
+        let id = first_id + i as u16;
+        group.push(image.width);
+        group.push(image.height);
+        group.push(image.color_count);
+        group.push(0);
+        res::push_u16(&mut group, image.planes);
+        res::push_u16(&mut group, image.bit_count);
+        res::push_u32(&mut group, image.data.len() as u32);
+        res::push_u16(&mut group, id);
+        let mut r = Resource::new(ResourceId::Ordinal(RT_ICON), ResourceId::Ordinal(id), language, image.data);
+        // MOVEABLE | DISCARDABLE
+        r.memory_flags = 0x1010;
+        resources.push(r);
+    }
+    let mut r = Resource::new(ResourceId::Ordinal(RT_GROUP_ICON), name, language, group);
+    r.memory_flags = 0x1030;
+    resources.push(r);
+    resources
+}
+
+/// Convert a cursor into its `RT_CURSOR` resources and the `RT_GROUP_CURSOR`
+///
+/// Every image is prefixed with its hotspot. In the group, the sizes are 16-bit values
+/// and the height of a bitmap counts the transparency mask as well.
+pub fn cursor_resources(name: ResourceId, images: Vec<IconImage>, first_id: u16, language: u16) -> Vec<Resource> {
+    let mut group = Vec::new();
+    res::push_u16(&mut group, 0);
+    res::push_u16(&mut group, 2);
+    res::push_u16(&mut group, images.len() as u16);
+    let mut resources = Vec::with_capacity(images.len() + 1);
+    for (i, image) in images.into_iter().enumerate() {
+        let id = first_id + i as u16;
+        let format = image.format;
+        res::push_u16(&mut group, format.width as u16);
+        if format.png {
+            res::push_u16(&mut group, format.height as u16);
+            res::push_u16(&mut group, 1);
+        } else {
+            res::push_u16(&mut group, format.height as u16 * 2);
+            res::push_u16(&mut group, u16_at(&image.data, 12));
+        }
+        res::push_u16(&mut group, format.bit_count);
+        res::push_u32(&mut group, image.data.len() as u32 + 4);
+        res::push_u16(&mut group, id);
+        let mut data = Vec::with_capacity(image.data.len() + 4);
+        res::push_u16(&mut data, image.planes);
+        res::push_u16(&mut data, image.bit_count);
+        data.extend_from_slice(&image.data);
+        let mut r = Resource::new(ResourceId::Ordinal(RT_CURSOR), ResourceId::Ordinal(id), language, data);
+        r.memory_flags = 0x1010;
+        resources.push(r);
+    }
+    let mut r = Resource::new(ResourceId::Ordinal(RT_GROUP_CURSOR), name, language, group);
+    r.memory_flags = 0x1030;
+    resources.push(r);
+    resources
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]])
+}
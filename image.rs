@@ -0,0 +1,170 @@
+//! Loading, scaling and encoding the images of generated icons
+//!
+//! Icons are built from PNG files. Images are scaled down by averaging the covered source
+//! pixels with premultiplied alpha, so that transparent pixels do not darken the edges.
+
+use std::fs;
+use std::path::Path;
+
+use png;
+
+use error::{Error, Result};
+use res;
+
+/// An image with 8-bit RGBA pixels, row by row from the top
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Read a PNG file and convert it to RGBA
+pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Image> {
+    let path = path.as_ref();
+    let invalid = |e: png::DecodingError| {
+        Error::InvalidIcon {
+            path: path.to_path_buf(),
+            message: format!("could not read PNG: {}", e),
+        }
+    };
+    let mut decoder = png::Decoder::new(fs::File::open(path)?);
+    // palettes, transparency chunks and 16-bit channels are turned into 8-bit channels
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).map_err(invalid)?;
+    buf.truncate(frame.buffer_size());
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf.chunks(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&p| vec![p, p, p, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(Error::InvalidIcon {
+                path: path.to_path_buf(),
+                message: "could not expand the PNG palette".to_string(),
+            })
+        }
+    };
+    Ok(Image {
+        width: frame.width,
+        height: frame.height,
+        rgba,
+    })
+}
+
+impl Image {
+    /// Scale the image to `width` x `height`
+    pub fn resize(&self, width: u32, height: u32) -> Image {
+        // premultiplied alpha as floating point values
+        let src: Vec<f32> = self.rgba
+            .chunks(4)
+            .flat_map(|p| {
+                let a = p[3] as f32 / 255.0;
+                vec![p[0] as f32 * a, p[1] as f32 * a, p[2] as f32 * a, p[3] as f32]
+            })
+            .collect();
+        let (sw, sh, dw, dh) = (self.width as usize, self.height as usize, width as usize, height as usize);
+
+        let columns = weights(sw, dw);
+        let mut horizontal = vec![0.0; dw * sh * 4];
+        for y in 0..sh {
+            for (x, column) in columns.iter().enumerate() {
+                for &(sx, w) in column {
+                    for c in 0..4 {
+                        horizontal[(y * dw + x) * 4 + c] += src[(y * sw + sx) * 4 + c] * w;
+                    }
+                }
+            }
+        }
+        let rows = weights(sh, dh);
+        let mut scaled = vec![0.0; dw * dh * 4];
+        for (y, row) in rows.iter().enumerate() {
+            for &(sy, w) in row {
+                for x in 0..dw {
+                    for c in 0..4 {
+                        scaled[(y * dw + x) * 4 + c] += horizontal[(sy * dw + x) * 4 + c] * w;
+                    }
+                }
+            }
+        }
+
+        let rgba = scaled.chunks(4)
+            .flat_map(|p| {
+                let a = p[3] / 255.0;
+                let color = |v: f32| if a > 0.0 { (v / a).round().clamp(0.0, 255.0) as u8 } else { 0 };
+                vec![color(p[0]), color(p[1]), color(p[2]), p[3].round().clamp(0.0, 255.0) as u8]
+            })
+            .collect();
+        Image { width, height, rgba }
+    }
+
+    /// Encode as PNG, the format for large icon images
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut buf, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            // writing to memory does not fail
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&self.rgba).unwrap();
+        }
+        buf
+    }
+
+    /// Encode as icon bitmap: a `BITMAPINFOHEADER`, the 32-bit BGRA pixels from the
+    /// bottom up and the 1-bit transparency mask, that is only used by very old Windows versions
+    pub fn to_icon_bitmap(&self) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mask_stride = ((width + 31) & !31) / 8;
+        let mut buf = Vec::with_capacity(40 + width * height * 4 + mask_stride * height);
+        res::push_u32(&mut buf, 40);
+        res::push_u32(&mut buf, width as u32);
+        // the height counts both the pixels and the mask
+        res::push_u32(&mut buf, height as u32 * 2);
+        res::push_u16(&mut buf, 1); // Planes
+        res::push_u16(&mut buf, 32); // BitCount
+        res::push_u32(&mut buf, 0); // BI_RGB
+        res::push_u32(&mut buf, (width * height * 4 + mask_stride * height) as u32);
+        res::push_u32(&mut buf, 0); // XPelsPerMeter
+        res::push_u32(&mut buf, 0); // YPelsPerMeter
+        res::push_u32(&mut buf, 0); // ClrUsed
+        res::push_u32(&mut buf, 0); // ClrImportant
+        for row in self.rgba.chunks(width * 4).rev() {
+            for p in row.chunks(4) {
+                buf.extend_from_slice(&[p[2], p[1], p[0], p[3]]);
+            }
+        }
+        for row in self.rgba.chunks(width * 4).rev() {
+            let mut mask = vec![0u8; mask_stride];
+            for (x, p) in row.chunks(4).enumerate() {
+                if p[3] == 0 {
+                    mask[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            buf.extend_from_slice(&mask);
+        }
+        buf
+    }
+}
+
+/// For every destination pixel, the source pixels it covers and how much of them
+fn weights(src: usize, dst: usize) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f32 / dst as f32;
+    (0..dst).map(|d| {
+        let start = d as f32 * scale;
+        let end = start + scale;
+        let mut covered = Vec::new();
+        let mut s = start.floor() as usize;
+        while (s as f32) < end && s < src {
+            let overlap = end.min(s as f32 + 1.0) - start.max(s as f32);
+            if overlap > 0.0 {
+                covered.push((s, overlap / scale));
+            }
+            s += 1;
+        }
+        covered
+    }).collect()
+}
@@ -11,16 +11,15 @@
 //!
 //! ```rust
 //! # extern crate winres;
-//! # use std::io;
-//! # fn test_main() -> io::Result<()> {
-//! if cfg!(target_os = "windows") {
+//! # fn test_main() -> winres::Result<()> {
+//! if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
 //!     let mut res = winres::WindowsResource::new();
 //!     res.set_icon("test.ico")
 //! #      .set_output_directory(".")
 //!        .set("InternalName", "TEST.EXE")
 //!        // manually set version 1.0.0.0
 //!        .set_version_info(winres::VersionInfo::PRODUCTVERSION, 0x0001000000000000);
-//!     try!(res.compile());
+//!     res.compile()?;
 //! }
 //! # Ok(())
 //! # }
@@ -42,33 +41,110 @@
 //! using Rust GNU 64-bit you have to use MinGW64. For MSVC this is simpler as (recent) Windows
 //! SDK always installs both versions on a 64-bit system.
 //!
+//! # Errors
+//!
+//! All fallible functions return a [`winres::Error`], which tells apart a missing toolkit,
+//! a failing resource compiler, an unusable icon or manifest and invalid metadata in
+//! `Cargo.toml`. It converts into `io::Error`, so it can be used with `?` in functions
+//! returning `io::Result`.
+//!
+//! ```rust
+//! # extern crate winres;
+//! # fn main() {
+//! if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+//!     let mut res = winres::WindowsResource::new();
+//!     match res.compile() {
+//!         Err(winres::Error::ToolkitNotFound(msg)) => println!("cargo:warning={}", msg),
+//!         Err(e) => panic!("{}", e),
+//!         Ok(()) => {}
+//!     }
+//! }
+//! # }
+//! ```
+//!
 //! [`WindowsResorce::compile()`]: struct.WindowsResource.html#method.compile
 //! [`WindowsResource::new()`]: struct.WindowsResource.html#method.new
+//! [`winres::Error`]: enum.Error.html
 
 use std::env;
-use std::path::{PathBuf, Path};
+use std::path::{Component, PathBuf, Path};
 use std::process;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::prelude::*;
 use std::fs;
-use std::error::Error;
 
+extern crate png;
+extern crate roxmltree;
 extern crate toml;
 
+mod bmp;
+mod accelerator;
+mod coff;
+mod dialog;
+mod diagnostic;
+mod error;
+mod ico;
+mod image;
+mod manifest;
+mod menu;
+mod message;
+mod res;
+mod version;
+
+pub use accelerator::{Accelerators, Modifiers};
+pub use diagnostic::{Diagnostic, Severity};
+pub use dialog::{Control, Dialog};
+pub use error::{Error, Result};
+pub use ico::{icon_formats, IconFormat};
+pub use manifest::{DpiAwareness, ExecutionLevel, Manifest, WindowsVersion};
+pub use menu::{Menu, MenuItem};
+pub use message::{MessageSeverity, MessageTable};
+pub use res::{ResourceData, ResourceId};
+pub use version::{FileFlags, FileOs, FileSubtype, FileType, VersionStrategy};
+
+use res::Resource;
+
 /// The compiler version defines which toolkit we have to use.
-/// The value is defined by the value of `cfg!(target_env=)`
+/// The value is defined by the compilation target, see [`WindowsResource::toolkit()`]
+///
+/// [`WindowsResource::toolkit()`]: struct.WindowsResource.html#method.toolkit
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Toolkit {
     /// use Microsoft Visual C and Windows SDK
     MSVC,
     /// use GNU Bintools
     GNU,
-    /// neiter `cfg!(target_env="msvc")` nor `cfg!(target_env="gnu")` was set.
+    /// the target environment is neither `"msvc"` nor `"gnu"`.
     Unknown,
 }
 
+/// The program that turns the resource description into something the linker understands
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ResourceCompiler {
+    /// use the resource compiler of the toolkit, i.e., `rc.exe` or `windres.exe`
+    Toolkit,
+    /// use the resource compiler built into this crate
+    ///
+    /// The resource is written directly in the binary `.res` format, or as an object file
+    /// for the GNU toolkit, no resource compiler, archiver or SDK has to be installed.
+    /// This cannot compile a resource file set with [`set_resource_file()`]. For the GNU
+    /// toolkit, the object is linked with the `+whole-archive` modifier of Rust 1.61.
+    ///
+    /// [`set_resource_file()`]: struct.WindowsResource.html#method.set_resource_file
+    Builtin,
+    /// use the LLVM tools, i.e., `llvm-rc` for MSVC and `llvm-windres` for GNU
+    ///
+    /// If `llvm-windres` is missing, `llvm-rc` and `llvm-cvtres` are used instead. The tools
+    /// are searched in the toolkit path first, then in `%PATH%`. For the GNU toolkit, the
+    /// object is linked like the one of `Builtin`, which needs Rust 1.61.
+    Llvm,
+}
+
 /// Version info field names
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub enum VersionInfo {
     /// The version value consists of four 16 bit words, e.g.,
     /// `MAJOR << 48 | MINOR << 32 | PATCH << 16 | RELEASE`
@@ -89,26 +165,109 @@ pub enum VersionInfo {
     FILEFLAGS,
 }
 
+/// The artifacts of the package a compiled resource is linked into
+#[derive(Clone, Copy)]
+enum LinkTarget<'a> {
+    /// every binary and library
+    Package,
+    /// only the binary with this name
+    Bin(&'a str),
+}
+
+impl<'a> LinkTarget<'a> {
+    /// The file name of the outputs without extension
+    fn file_stem(self) -> String {
+        match self {
+            LinkTarget::Package => "resource".to_string(),
+            LinkTarget::Bin(name) => format!("resource-{}", name),
+        }
+    }
+}
+
+/// Resources embedded from files, other than icons
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum FileKind {
+    Cursor,
+    AnimatedCursor,
+    AnimatedIcon,
+    Bitmap,
+}
+
+impl FileKind {
+    /// The resource type in a resource script
+    ///
+    /// Not every resource compiler knows `ANICURSOR` and `ANIICON`, but all of them store
+    /// a file with a numeric type unchanged, which is what these are.
+    fn rc_type(self) -> &'static str {
+        match self {
+            FileKind::Cursor => "CURSOR",
+            FileKind::AnimatedCursor => "21",
+            FileKind::AnimatedIcon => "22",
+            FileKind::Bitmap => "BITMAP",
+        }
+    }
+}
+
+/// The contents of an embedded file
+enum FileData {
+    Images(Vec<ico::IconImage>),
+    Raw(Vec<u8>),
+}
+
+#[derive(Clone)]
 pub struct WindowsResource {
     toolkit_path: String,
+    windres_path: Option<String>,
+    ar_path: Option<String>,
     properties: HashMap<String, String>,
+    translations: BTreeMap<u16, HashMap<String, String>>,
     version_info: HashMap<VersionInfo, u64>,
     rc_file: Option<String>,
-    icon: Option<String>,
+    icons: BTreeMap<ResourceId, String>,
+    main_icon: Option<ResourceId>,
+    files: Vec<(FileKind, ResourceId, String)>,
+    strings: BTreeMap<u16, String>,
+    string_translations: BTreeMap<u16, BTreeMap<u16, String>>,
+    dialogs: BTreeMap<ResourceId, Dialog>,
+    menus: BTreeMap<ResourceId, Menu>,
+    accelerators: BTreeMap<ResourceId, Accelerators>,
+    custom: Vec<(ResourceId, ResourceId, ResourceData)>,
+    messages: Option<MessageTable>,
+    /// files read for the settings, e.g., `Cargo.toml`, a change has to run the build script again
+    tracked_files: Vec<PathBuf>,
+    track_files: bool,
     language: u16,
     manifest: Option<String>,
     manifest_file: Option<String>,
+    app_manifest: Option<Manifest>,
     output_directory: String,
+    compiler: ResourceCompiler,
+    /// the `bin.NAME` metadata tables and the directory of their `Cargo.toml`
+    bin_metadata: BTreeMap<String, (toml::value::Table, PathBuf)>,
 }
 
 impl WindowsResource {
+    /// Get the toolkit for the current compilation target.
+    ///
+    /// A build script is compiled for the host, so `cfg!(target_env)` would describe the
+    /// machine running the build, not the one the resource is compiled for. Instead we look
+    /// at the `CARGO_CFG_TARGET_ENV` and `TARGET` variables cargo sets for build scripts.
+    /// Only if neither is set (i.e. we are not run from a build script) the host
+    /// environment is used.
     pub fn toolkit() -> Toolkit {
-        if cfg!(target_env = "gnu") {
-            Toolkit::GNU
-        } else if cfg!(target_env = "msvc") {
-            Toolkit::MSVC
-        } else {
-            Toolkit::Unknown
+        let target_env = match env::var("CARGO_CFG_TARGET_ENV") {
+            Ok(target_env) => target_env,
+            Err(_) => match env::var("TARGET") {
+                Ok(target) => target.rsplit('-').next().unwrap_or("").to_string(),
+                Err(_) if cfg!(target_env = "gnu") => "gnu".to_string(),
+                Err(_) if cfg!(target_env = "msvc") => "msvc".to_string(),
+                Err(_) => String::new(),
+            },
+        };
+        match target_env.as_str() {
+            "gnu" | "gnullvm" => Toolkit::GNU,
+            "msvc" => Toolkit::MSVC,
+            _ => Toolkit::Unknown,
         }
     }
 
@@ -126,10 +285,45 @@ impl WindowsResource {
     ///
     /// Furthermore if a section `package.metadata.winres` exists
     /// in `Cargo.toml` it will be parsed. Values in this section take precedence
-    /// over the values provided natively by cargo. Only the string table
-    /// of the version struct can be set this way.
-    /// Additionally, the language field is set to neutral (i.e. `0`),
-    /// and no icon is set. These settings have to be done programmatically.
+    /// over the values provided natively by cargo. String values with other keys than
+    /// the ones listed below are added to the string table of the version struct.
+    ///
+    /// | Key                  | Value                                  | Same as                     |
+    /// |----------------------|----------------------------------------|-----------------------------|
+    /// | `icon`               | path of an `.ico` or `.png` file       | [`set_icon()`]              |
+    /// | `icons`              | table of ids and paths                 | [`add_icon()`]              |
+    /// | `cursors`            | table of ids and `.cur` or `.ani` paths| [`add_cursor()`]            |
+    /// | `bitmaps`            | table of ids and `.bmp` paths          | [`add_bitmap()`]            |
+    /// | `strings`            | table of numeric ids and strings       | [`add_string()`]            |
+    /// | `rcdata`             | table of ids and paths                 | [`add_rcdata()`]            |
+    /// | `message-file`       | path of a message text file (`.mc`)    | [`set_message_table()`]     |
+    /// | `manifest`           | the manifest XML, or a table           | [`set_manifest()`]          |
+    /// | `manifest-file`      | path of a manifest file                | [`set_manifest_file()`]     |
+    /// | `language`           | language id, e.g., `1033` or `"0x409"` | [`set_language()`]          |
+    /// | `version-strategy`   | e.g., `"pre-release-channel"`          | [`set_version_strategy()`]  |
+    /// | `file-version`       | e.g., `"1.2.3.4"`                      | `FILEVERSION`               |
+    /// | `product-version`    | e.g., `"1.2"`                          | `PRODUCTVERSION`            |
+    /// | `file-flags`         | e.g., `["prerelease", "debug"]`        | [`set_file_flags()`]        |
+    /// | `file-os`            | e.g., `"nt-windows32"`                 | [`set_file_os()`]           |
+    /// | `file-type`          | e.g., `"dll"`                          | [`set_file_type()`]         |
+    /// | `file-subtype`       | e.g., `"font-truetype"` or a number    | [`set_file_subtype()`]      |
+    /// | `resource-compiler`  | `"toolkit"`, `"builtin"` or `"llvm"`   | [`set_resource_compiler()`] |
+    /// | `output-directory`   | path                                   | [`set_output_directory()`]  |
+    ///
+    /// Names of enum values are written in lowercase with dashes instead of underscores.
+    /// A number as `file-subtype` is the identifier of a virtual device and needs `file-type = "vxd"`,
+    /// the subtypes of drivers and fonts are given by name.
+    /// Relative paths are relative to the directory of `Cargo.toml`. A `manifest` table
+    /// builds a [`Manifest`], with the keys `execution-level`, `ui-access`, `dpi-awareness`,
+    /// `long-path-aware`, `utf8-code-page`, `supported-os`, `common-controls-v6` and
+    /// `segment-heap`, e.g., `supported-os = "windows8.1"`.
+    ///
+    /// If the package is part of a workspace, the `workspace.metadata.winres` section of the
+    /// workspace's `Cargo.toml` provides defaults for all of its members. Its relative paths
+    /// are relative to the workspace root. A member can override every key, or state that a
+    /// key has to be inherited with `key.workspace = true`. Fields of the `package` section
+    /// inherited from the workspace, e.g., `version.workspace = true`, are resolved by cargo.
+    /// Without metadata, the language field is set to neutral (i.e. `0`), and no icon is set.
     ///
     /// `Cargo.toml` files have to be written in UTF-8, we support all valid UTF-8 strings
     /// provided.
@@ -140,6 +334,9 @@ impl WindowsResource {
     /// OriginalFilename = "testing.exe"
     /// FileDescription = "⛄❤☕"
     /// LegalCopyright = "Copyright © 2016"
+    /// icon = "assets/app.ico"
+    /// language = 0x0409
+    /// file-flags = ["prerelease"]
     /// ```
     ///
     /// The version info struct is set to some values
@@ -155,26 +352,50 @@ impl WindowsResource {
     /// | `FILEFLAGSMASK`      | `VS_FFI_FILEFLAGSMASK (0x3F)`|
     /// | `FILEFLAGS`          | `0x0`                        |
     ///
+    /// # Panics
+    ///
+    /// If we are not run from a build script, or if the metadata in `Cargo.toml` is
+    /// invalid. Use [`try_new()`] to handle these errors.
+    ///
+    /// [`try_new()`]: #method.try_new
+    /// [`set_icon()`]: #method.set_icon
+    /// [`add_icon()`]: #method.add_icon
+    /// [`add_cursor()`]: #method.add_cursor
+    /// [`add_bitmap()`]: #method.add_bitmap
+    /// [`add_string()`]: #method.add_string
+    /// [`add_rcdata()`]: #method.add_rcdata
+    /// [`set_message_table()`]: #method.set_message_table
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`Manifest`]: struct.Manifest.html
+    /// [`set_language()`]: #method.set_language
+    /// [`set_version_strategy()`]: #method.set_version_strategy
+    /// [`set_file_flags()`]: #method.set_file_flags
+    /// [`set_file_os()`]: #method.set_file_os
+    /// [`set_file_type()`]: #method.set_file_type
+    /// [`set_file_subtype()`]: #method.set_file_subtype
+    /// [`set_resource_compiler()`]: #method.set_resource_compiler
+    /// [`set_output_directory()`]: #method.set_output_directory
     pub fn new() -> Self {
+        match WindowsResource::try_new() {
+            Ok(res) => res,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Same as [`new()`], but returns an error instead of panicking
+    ///
+    /// [`new()`]: #method.new
+    pub fn try_new() -> Result<Self> {
         let mut props: HashMap<String, String> = HashMap::new();
         let mut ver: HashMap<VersionInfo, u64> = HashMap::new();
 
-        props.insert("FileVersion".to_string(),
-                     env::var("CARGO_PKG_VERSION").unwrap().to_string());
-        props.insert("ProductVersion".to_string(),
-                     env::var("CARGO_PKG_VERSION").unwrap().to_string());
-        props.insert("ProductName".to_string(),
-                     env::var("CARGO_PKG_NAME").unwrap().to_string());
-        props.insert("FileDescription".to_string(),
-                     env::var("CARGO_PKG_DESCRIPTION").unwrap().to_string());
-
-        parse_cargo_toml(&mut props).unwrap();
-
-        let mut version = 0 as u64;
-        version |= env::var("CARGO_PKG_VERSION_MAJOR").unwrap().parse().unwrap_or(0) << 48;
-        version |= env::var("CARGO_PKG_VERSION_MINOR").unwrap().parse().unwrap_or(0) << 32;
-        version |= env::var("CARGO_PKG_VERSION_PATCH").unwrap().parse().unwrap_or(0) << 16;
-        // version |= env::var("CARGO_PKG_VERSION_PRE").unwrap().parse().unwrap_or(0);
+        props.insert("FileVersion".to_string(), cargo_env("CARGO_PKG_VERSION")?);
+        props.insert("ProductVersion".to_string(), cargo_env("CARGO_PKG_VERSION")?);
+        props.insert("ProductName".to_string(), cargo_env("CARGO_PKG_NAME")?);
+        props.insert("FileDescription".to_string(), cargo_env("CARGO_PKG_DESCRIPTION")?);
+
+        let version = cargo_version(VersionStrategy::Zero)?;
         ver.insert(VersionInfo::FILEVERSION, version);
         ver.insert(VersionInfo::PRODUCTVERSION, version);
         ver.insert(VersionInfo::FILEOS, 0x00040004);
@@ -184,21 +405,225 @@ impl WindowsResource {
         ver.insert(VersionInfo::FILEFLAGS, 0);
 
         let sdk = match get_sdk() {
-            Ok(mut v) => v.pop().unwrap(),
+            Ok(mut v) => v.pop().unwrap_or_default(),
             Err(_) => String::new(),
         };
 
-        WindowsResource {
+        let mut res = WindowsResource {
             toolkit_path: sdk,
+            windres_path: None,
+            ar_path: None,
             properties: props,
+            translations: BTreeMap::new(),
             version_info: ver,
             rc_file: None,
-            icon: None,
+            icons: BTreeMap::new(),
+            main_icon: None,
+            files: Vec::new(),
+            strings: BTreeMap::new(),
+            string_translations: BTreeMap::new(),
+            dialogs: BTreeMap::new(),
+            menus: BTreeMap::new(),
+            accelerators: BTreeMap::new(),
+            custom: Vec::new(),
+            messages: None,
+            tracked_files: Vec::new(),
+            track_files: false,
             language: 0,
             manifest: None,
             manifest_file: None,
+            app_manifest: None,
             output_directory: env::var("OUT_DIR").unwrap_or(".".to_string()),
+            compiler: ResourceCompiler::Toolkit,
+            bin_metadata: BTreeMap::new(),
+        };
+
+        let dir = PathBuf::from(cargo_env("CARGO_MANIFEST_DIR")?);
+        let cargo_toml = parse_cargo_toml(&dir)?;
+        res.tracked_files.push(dir.join("Cargo.toml"));
+        let mut metadata = winres_table(&cargo_toml, "package")?;
+        let workspace = match find_workspace_root(&dir, &cargo_toml)? {
+            Some(root) if root == dir => winres_table(&cargo_toml, "workspace")?.map(|m| (m, root)),
+            Some(root) => {
+                res.tracked_files.push(root.join("Cargo.toml"));
+                winres_table(&parse_cargo_toml(&root)?, "workspace")?.map(|m| (m, root))
+            }
+            None => None,
+        };
+        if metadata.is_none() && workspace.is_none() {
+            println!("package.metadata.winres does not exist");
+        }
+        if let Some((defaults, root)) = workspace {
+            if let Some(ref mut metadata) = metadata {
+                inherit_workspace_keys(metadata, &defaults)?;
+            }
+            res.apply_metadata(&defaults, &root)?;
+        }
+        if let Some(metadata) = metadata {
+            res.apply_metadata(&metadata, &dir)?;
+        }
+        Ok(res)
+    }
+
+    /// Apply the settings of a `package.metadata.winres` table
+    ///
+    /// Relative paths are resolved against `dir`.
+    fn apply_metadata(&mut self, metadata: &toml::value::Table, dir: &Path) -> Result<()> {
+        // the order matters, e.g., icons from PNG files are written to the output directory,
+        // the file type resets the subtype and the version strategy overwrites the versions
+        for &key in &METADATA_KEYS {
+            let value = match metadata.get(key) {
+                Some(value) => value,
+                None => continue,
+            };
+            let name = format!("package.metadata.winres.{}", key);
+            let path = |value: &toml::Value| -> Result<String> {
+                Ok(dir.join(metadata_str(&name, value)?).to_string_lossy().into_owned())
+            };
+            match key {
+                "icon" => {
+                    let icon = path(value)?;
+                    if icon.to_lowercase().ends_with(".png") {
+                        self.set_icon_from_png(&icon)?;
+                    } else {
+                        self.set_icon(&icon);
+                    }
+                }
+                "icons" => {
+                    let icons = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (id, icon) in icons {
+                        self.add_icon(metadata_id(id), &path(icon)?);
+                    }
+                }
+                "cursors" => {
+                    let cursors = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (id, cursor) in cursors {
+                        let cursor = path(cursor)?;
+                        if cursor.to_lowercase().ends_with(".ani") {
+                            self.add_animated_cursor(metadata_id(id), &cursor);
+                        } else {
+                            self.add_cursor(metadata_id(id), &cursor);
+                        }
+                    }
+                }
+                "bitmaps" => {
+                    let bitmaps = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (id, bitmap) in bitmaps {
+                        self.add_bitmap(metadata_id(id), &path(bitmap)?);
+                    }
+                }
+                "strings" => {
+                    let strings = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (id, text) in strings {
+                        let id = id.parse::<u16>()
+                            .map_err(|_| Error::Metadata(format!("{}: \"{}\" is not a number between 0 and 65535", name, id)))?;
+                        self.add_string(id, metadata_str(&format!("{}.{}", name, id), text)?);
+                    }
+                }
+                "rcdata" => {
+                    let files = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (id, file) in files {
+                        self.add_rcdata(metadata_id(id), &path(file)?);
+                    }
+                }
+                "message-file" => {
+                    self.set_message_table(&MessageTable::from_mc_file(path(value)?)?);
+                }
+                "manifest" => {
+                    match value.as_table() {
+                        Some(table) => self.set_application_manifest(&metadata_manifest(&name, table)?),
+                        None => self.set_manifest(metadata_str(&name, value)?),
+                    };
+                }
+                "manifest-file" => {
+                    self.set_manifest_file(&path(value)?);
+                }
+                "language" => {
+                    self.set_language(metadata_u16(&name, value)?);
+                }
+                "version-strategy" => {
+                    let strategy = metadata_str(&name, value)?;
+                    let strategy = VersionStrategy::from_name(strategy)
+                        .ok_or_else(|| Error::Metadata(format!("{} \"{}\" is unknown", name, strategy)))?;
+                    self.set_version_strategy(strategy)?;
+                }
+                "file-version" | "product-version" => {
+                    let version = version::parse_numeric_version(metadata_str(&name, value)?)
+                        .map_err(|e| Error::Metadata(format!("{}: {}", name, e)))?;
+                    let field = if key == "file-version" { VersionInfo::FILEVERSION } else { VersionInfo::PRODUCTVERSION };
+                    self.set_version_info(field, version);
+                }
+                "file-flags" => {
+                    let list = value.as_array()
+                        .ok_or_else(|| Error::Metadata(format!("{} is not a list", name)))?;
+                    let mut flags = FileFlags::empty();
+                    for flag in list {
+                        let flag = metadata_str(&name, flag)?;
+                        flags |= FileFlags::from_name(flag)
+                            .ok_or_else(|| Error::Metadata(format!("{} \"{}\" is unknown", name, flag)))?;
+                    }
+                    self.set_file_flags(flags);
+                }
+                "file-os" => {
+                    let os = metadata_str(&name, value)?;
+                    let os = FileOs::from_name(os).ok_or_else(|| Error::Metadata(format!("{} \"{}\" is unknown", name, os)))?;
+                    self.set_file_os(os);
+                }
+                "file-type" => {
+                    let file_type = metadata_str(&name, value)?;
+                    let file_type = FileType::from_name(file_type)
+                        .ok_or_else(|| Error::Metadata(format!("{} \"{}\" is unknown", name, file_type)))?;
+                    self.set_file_type(file_type);
+                }
+                "file-subtype" => {
+                    let subtype = match value.as_integer() {
+                        Some(_) if self.version_info.get(&VersionInfo::FILETYPE) != Some(&(FileType::VXD.value() as u64)) => {
+                            return Err(Error::Metadata(format!("{} can only be a number for file-type \"vxd\", \
+                                                                use a name like \"drv-printer\" otherwise", name)));
+                        }
+                        Some(id) if id >= 0 && id <= u32::MAX as i64 => FileSubtype::VXD(id as u32),
+                        _ => {
+                            let subtype = metadata_str(&name, value)?;
+                            FileSubtype::from_name(subtype)
+                                .ok_or_else(|| Error::Metadata(format!("{} \"{}\" is unknown", name, subtype)))?
+                        }
+                    };
+                    self.set_file_subtype(subtype)?;
+                }
+                "resource-compiler" => {
+                    let compiler = match metadata_str(&name, value)? {
+                        "toolkit" => ResourceCompiler::Toolkit,
+                        "builtin" => ResourceCompiler::Builtin,
+                        "llvm" => ResourceCompiler::Llvm,
+                        c => return Err(Error::Metadata(format!("{} \"{}\" is unknown", name, c))),
+                    };
+                    self.set_resource_compiler(compiler);
+                }
+                "output-directory" => {
+                    self.set_output_directory(&path(value)?);
+                }
+                "bin" => {
+                    let bins = value.as_table().ok_or_else(|| Error::Metadata(format!("{} is not a table", name)))?;
+                    for (bin, metadata) in bins {
+                        let metadata = metadata.as_table()
+                            .ok_or_else(|| Error::Metadata(format!("{}.{} is not a table", name, bin)))?;
+                        self.bin_metadata.insert(bin.clone(), (metadata.clone(), dir.to_path_buf()));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        for (k, v) in metadata {
+            if METADATA_KEYS.contains(&k.as_str()) {
+                continue;
+            }
+            if let Some(v) = v.as_str() {
+                self.properties.insert(k.clone(), v.to_string());
+            } else {
+                return Err(Error::Metadata(format!("package.metadata.winres.{} is not a string", k)));
+            }
         }
+        Ok(())
     }
 
     /// Set string properties of the version info struct.
@@ -223,11 +648,195 @@ impl WindowsResource {
     ///
     /// It is possible to use arbirtrary field names, but Windows Explorer and other
     /// tools might not show them.
-    pub fn set<'a>(&mut self, name: &'a str, value: &'a str) -> &mut Self {
+    ///
+    /// These values are written for the language set with [`set_language()`], for
+    /// other languages see [`set_for_language()`].
+    ///
+    /// [`set_language()`]: #method.set_language
+    /// [`set_for_language()`]: #method.set_for_language
+    pub fn set(&mut self, name: &str, value: &str) -> &mut Self {
         self.properties.insert(name.to_string(), value.to_string());
         self
     }
 
+    /// Set a string property of the version info struct for a specific language.
+    ///
+    /// Every language gets its own string table in the version info and is listed as
+    /// a translation. A language starts with the values set by [`set()`], so only the
+    /// properties that differ have to be set. Values for the language set with
+    /// [`set_language()`] override those set by [`set()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_language(0x0409)
+    ///    .set("FileDescription", "Frobnicator")
+    ///    .set_for_language(0x0407, "FileDescription", "Frobnikator");
+    /// # res.write_res_file(std::env::temp_dir().join("languages.res")).unwrap();
+    /// ```
+    ///
+    /// [`set()`]: #method.set
+    /// [`set_language()`]: #method.set_language
+    pub fn set_for_language(&mut self, language: u16, name: &str, value: &str) -> &mut Self {
+        self.translations
+            .entry(language)
+            .or_default()
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// The string tables of the version info, the main language first
+    fn string_tables(&self) -> Vec<(u16, HashMap<String, String>)> {
+        let mut main = self.properties.clone();
+        if let Some(props) = self.translations.get(&self.language) {
+            main.extend(props.clone());
+        }
+        let mut tables = vec![(self.language, main)];
+        for (language, props) in self.translations.iter().filter(|&(l, _)| *l != self.language) {
+            let mut table = self.properties.clone();
+            table.extend(props.clone());
+            tables.push((*language, table));
+        }
+        tables
+    }
+
+    /// Add a string to the string table
+    ///
+    /// The string is loaded with `LoadString()`, the id is a number. Strings are stored
+    /// for the language set with [`set_language()`], for other languages see
+    /// [`add_string_table()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_string(101, "File not found")
+    ///    .add_string(102, "Do you want to save the changes?");
+    /// ```
+    ///
+    /// [`set_language()`]: #method.set_language
+    /// [`add_string_table()`]: #method.add_string_table
+    pub fn add_string(&mut self, id: u16, text: &str) -> &mut Self {
+        self.strings.insert(id, text.to_string());
+        self
+    }
+
+    /// Add the strings of a language to the string table
+    ///
+    /// Windows picks the strings of the user's language, if they exist. Strings added for the
+    /// language set with [`set_language()`] override those added with [`add_string()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut german = BTreeMap::new();
+    /// german.insert(101, "Datei nicht gefunden");
+    /// german.insert(102, "Möchten Sie die Änderungen speichern?");
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_language(0x0409)
+    ///    .add_string(101, "File not found")
+    ///    .add_string(102, "Do you want to save the changes?")
+    ///    .add_string_table(0x0407, german);
+    /// # res.write_res_file(std::env::temp_dir().join("strings.res")).unwrap();
+    /// ```
+    ///
+    /// [`set_language()`]: #method.set_language
+    /// [`add_string()`]: #method.add_string
+    pub fn add_string_table<S: Into<String>, T: IntoIterator<Item = (u16, S)>>(&mut self, language: u16, strings: T) -> &mut Self {
+        self.string_translations
+            .entry(language)
+            .or_default()
+            .extend(strings.into_iter().map(|(id, text)| (id, text.into())));
+        self
+    }
+
+    /// Add a dialog box
+    ///
+    /// The id is a number or a name, it is used to create the dialog with `DialogBox()` or
+    /// `CreateDialog()`. See [`Dialog`] for an example.
+    ///
+    /// [`Dialog`]: struct.Dialog.html
+    pub fn add_dialog<I: Into<ResourceId>>(&mut self, id: I, dialog: &Dialog) -> &mut Self {
+        self.dialogs.insert(id.into(), dialog.clone());
+        self
+    }
+
+    /// Check the ids and the controls of the dialogs
+    fn check_dialogs(&self) -> Result<()> {
+        for (id, dialog) in &self.dialogs {
+            id.check()
+                .and_then(|_| dialog.check())
+                .map_err(|message| Error::InvalidResource(format!("dialog {}: {}", id, message)))?;
+        }
+        Ok(())
+    }
+
+    /// Add a menu
+    ///
+    /// The id is a number or a name, it is used to load the menu with `LoadMenu()`.
+    /// See [`Menu`] for an example.
+    ///
+    /// [`Menu`]: struct.Menu.html
+    pub fn add_menu<I: Into<ResourceId>>(&mut self, id: I, menu: &Menu) -> &mut Self {
+        self.menus.insert(id.into(), menu.clone());
+        self
+    }
+
+    /// Add an accelerator table
+    ///
+    /// The id is a number or a name, it is used to load the table with `LoadAccelerators()`.
+    /// See [`Accelerators`] for an example.
+    ///
+    /// [`Accelerators`]: struct.Accelerators.html
+    pub fn add_accelerators<I: Into<ResourceId>>(&mut self, id: I, accelerators: &Accelerators) -> &mut Self {
+        self.accelerators.insert(id.into(), accelerators.clone());
+        self
+    }
+
+    /// Check the ids and the items of the menus and accelerator tables
+    fn check_menus(&self) -> Result<()> {
+        for (id, menu) in &self.menus {
+            id.check()
+                .and_then(|_| menu.check())
+                .map_err(|message| Error::InvalidResource(format!("menu {}: {}", id, message)))?;
+        }
+        for (id, accelerators) in &self.accelerators {
+            id.check()
+                .and_then(|_| accelerators.check())
+                .map_err(|message| Error::InvalidResource(format!("accelerators {}: {}", id, message)))?;
+        }
+        Ok(())
+    }
+
+    /// The string tables of all languages, with the main language's strings merged
+    fn string_table_languages(&self) -> BTreeMap<u16, BTreeMap<u16, String>> {
+        let mut languages = self.string_translations.clone();
+        if !self.strings.is_empty() {
+            let main = languages.entry(self.language).or_default();
+            for (id, text) in &self.strings {
+                main.entry(*id).or_insert_with(|| text.clone());
+            }
+        }
+        languages
+    }
+
+    /// Check that the strings fit into the string table
+    fn check_strings(&self) -> Result<()> {
+        for (language, strings) in self.string_table_languages() {
+            for (id, text) in strings {
+                if text.encode_utf16().count() > 0xFFFF {
+                    return Err(Error::Unsupported(format!("String {} of language {:#x} is longer than 65535 characters",
+                                                          id, language)));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Set the correct path for the toolkit.
     ///
     /// For the GNU toolkit this has to be the path where MinGW
@@ -237,32 +846,66 @@ impl WindowsResource {
     /// For MSVC the Windows SDK has to be installed. It comes with the resource compiler
     /// `rc.exe`. This should be set to the root directory of the Windows SDK, e.g.,
     /// `"C:\Program Files (x86)\Windows Kits\10`
-    pub fn set_toolkit_path<'a>(&mut self, path: &'a str) -> &mut Self {
+    ///
+    /// If the tools are installed with a target prefix, e.g., `x86_64-w64-mingw32-windres`
+    /// as most Linux distributions do, the prefixed names are tried first. The prefix
+    /// is derived from the target architecture.
+    /// To use other programs see [`set_windres_path()`] and [`set_ar_path()`].
+    ///
+    /// For [`ResourceCompiler::Llvm`] this is the directory containing `llvm-rc`, e.g.,
+    /// `"/usr/lib/llvm-14/bin"`.
+    ///
+    /// [`ResourceCompiler::Llvm`]: enum.ResourceCompiler.html#variant.Llvm
+    /// [`set_windres_path()`]: #method.set_windres_path
+    /// [`set_ar_path()`]: #method.set_ar_path
+    pub fn set_toolkit_path(&mut self, path: &str) -> &mut Self {
         self.toolkit_path = path.to_string();
         self
     }
 
+    /// Set the path to `windres.exe` of the GNU toolkit.
+    ///
+    /// This overrides the search in the toolkit path and `%PATH%`. The same can
+    /// be achieved without changing the build script, by setting the `WINRES_WINDRES`
+    /// environment variable.
+    pub fn set_windres_path(&mut self, path: &str) -> &mut Self {
+        self.windres_path = Some(path.to_string());
+        self
+    }
+
+    /// Set the path to `ar.exe` of the GNU toolkit.
+    ///
+    /// The environment variable for this setting is `WINRES_AR`.
+    /// See [`set_windres_path()`] for details.
+    ///
+    /// [`set_windres_path()`]: #method.set_windres_path
+    pub fn set_ar_path(&mut self, path: &str) -> &mut Self {
+        self.ar_path = Some(path.to_string());
+        self
+    }
+
     /// Set the user interface language of the file
     ///
     /// # Example
     ///
     /// ```
+    /// # #[cfg(windows)]
     /// extern crate winapi;
     /// extern crate winres;
-    /// # use std::io;
+    /// # #[cfg(windows)]
     /// fn main() {
-    ///   if cfg!(target_os = "windows") {
     ///     let mut res = winres::WindowsResource::new();
     /// #   res.set_output_directory(".");
-    ///     res.set_language(winapi::winnt::MAKELANGID(
-    ///         winapi::winnt::LANG_ENGLISH,
-    ///         winapi::winnt::SUBLANG_ENGLISH_US
+    ///     res.set_language(winapi::MAKELANGID(
+    ///         winapi::LANG_ENGLISH,
+    ///         winapi::SUBLANG_ENGLISH_US
     ///     ));
     ///     res.compile().unwrap();
-    ///   }
     /// }
+    /// # #[cfg(not(windows))]
+    /// # fn main() {}
     /// ```
-    /// For possible values look at the `winapi::winnt` contants, specificaly those,
+    /// For possible values look at the [`winapi::winnt`] contants, specificaly those,
     /// starting with `LANG_` and `SUBLANG`.
     ///
     /// [`make_language_id!`]: macro.make_language_id.html
@@ -297,164 +940,1016 @@ impl WindowsResource {
     /// Set an icon filename
     ///
     /// This icon need to be in `ico` format. The filename can be absolute
-    /// or relative to the projects root.
-    pub fn set_icon<'a>(&mut self, path: &'a str) -> &mut Self {
-        self.icon = Some(path.to_string());
-        self
+    /// or relative to the projects root. Other formats are rejected when compiling, and
+    /// warnings are printed for common problems, e.g., a missing 256x256 image.
+    ///
+    /// This is the main icon of the application with id `1`, see [`set_icon_with_id()`].
+    ///
+    /// [`set_icon_with_id()`]: #method.set_icon_with_id
+    pub fn set_icon(&mut self, path: &str) -> &mut Self {
+        self.set_icon_with_id(1, path)
     }
 
-    /// Set a version info struct property
-    /// Currently we only support numeric values, you have to look them up.
-    pub fn set_version_info(&mut self, field: VersionInfo, value: u64) -> &mut Self {
-        self.version_info.insert(field, value);
+    /// Set the main icon of the application with an explicit id
+    ///
+    /// Windows Explorer shows the first icon of an executable, which is the one with the
+    /// lowest id, where names come before numbers. Compiling fails if one of the icons added
+    /// with [`add_icon()`] would come before the main icon.
+    ///
+    /// [`add_icon()`]: #method.add_icon
+    pub fn set_icon_with_id<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        let id = id.into();
+        if let Some(main) = self.main_icon.take() {
+            self.icons.remove(&main);
+        }
+        self.icons.insert(id.clone(), path.to_string());
+        self.main_icon = Some(id);
         self
     }
 
-    /// Set the embedded manifest file
-    ///
-    /// # Example
+    /// Set the main icon from a PNG file
     ///
-    /// The following manifest will brand the exe as requesting administrator privileges.
-    /// Thus everytime it is executed, a Windows UAC dialog will appear.
+    /// An icon with images of 16, 24, 32, 48, 64 and 256 pixels is generated in the output
+    /// directory and used like one set with [`set_icon()`]. The image has to be square and
+    /// should have at least 256x256 pixels, larger sizes are left out.
     ///
-    /// ```rust
+    /// ```rust,no_run
+    /// # fn test_main() -> winres::Result<()> {
     /// let mut res = winres::WindowsResource::new();
-    /// res.set_manifest(r#"
-    /// <assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
-    /// <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
-    ///     <security>
-    ///         <requestedPrivileges>
-    ///             <requestedExecutionLevel level="requireAdministrator" uiAccess="false" />
-    ///         </requestedPrivileges>
-    ///     </security>
-    /// </trustInfo>
-    /// </assembly>
-    /// "#);
+    /// res.set_icon_from_png("assets/logo.png")?;
+    /// res.compile()?;
+    /// # Ok(())
+    /// # }
     /// ```
-    pub fn set_manifest<'a>(&mut self, manifest: &'a str) -> &mut Self {
-        self.manifest_file = None;
-        self.manifest = Some(manifest.to_string());
-        self
-    }
-
-    /// Some as [`set_manifest()`] but a filename can be provided and
-    /// file is included by the resource compieler itself.
-    /// This method works the same way as [`set_icon()`]
     ///
-    /// [`set_manifest()`]: #method.set_manifest
     /// [`set_icon()`]: #method.set_icon
-    pub fn set_manifest_file<'a>(&mut self, file: &'a str) -> &mut Self {
-        self.manifest_file = Some(file.to_string());
-        self.manifest = None;
-        self
+    pub fn set_icon_from_png(&mut self, path: &str) -> Result<&mut Self> {
+        self.set_icon_from_pngs(&[path])
     }
 
-    /// Write a resource file with the set values
-    pub fn write_resource_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut f = try!(fs::File::create(path));
-        // we don't need to include this, we use constants instead of macro names
-        // try!(write!(f, "#include <winver.h>\n"));
-
-        // use UTF8 as an encoding
-        // this makes it easier, since in rust all string are UTF8
-        try!(writeln!(f, "#pragma code_page(65001)"));
-        try!(writeln!(f, "1 VERSIONINFO"));
-        for (k, v) in self.version_info.iter() {
-            match *k {
-                VersionInfo::FILEVERSION |
-                VersionInfo::PRODUCTVERSION => {
-                    try!(writeln!(f,
-                                  "{:?} {}, {}, {}, {}",
-                                  k,
-                                  (*v >> 48) as u16,
-                                  (*v >> 32) as u16,
-                                  (*v >> 16) as u16,
-                                  *v as u16))
-                }
-                _ => try!(writeln!(f, "{:?} {:#x}", k, v)),
-            };
-        }
-        try!(writeln!(f, "{{\nBLOCK \"StringFileInfo\""));
-        try!(writeln!(f, "{{\nBLOCK \"{:04x}04b0\"\n{{", self.language));
-        for (k, v) in self.properties.iter() {
-            if !v.is_empty() {
-                try!(writeln!(f, "VALUE \"{}\", \"{}\"", k, v));
+    /// Set the main icon from PNG files of different sizes
+    ///
+    /// Small images often look better when they are drawn for their size. Every file is used
+    /// as is for its own size, the missing sizes of [`set_icon_from_png()`] are scaled down
+    /// from the next larger file. The icon is named after the first file and a hash of the
+    /// paths of all files, so that icons from files with the same name do not collide.
+    ///
+    /// ```rust,no_run
+    /// # fn test_main() -> winres::Result<()> {
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon_from_pngs(&["assets/logo-256.png", "assets/logo-32.png", "assets/logo-16.png"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`set_icon_from_png()`]: #method.set_icon_from_png
+    pub fn set_icon_from_pngs(&mut self, paths: &[&str]) -> Result<&mut Self> {
+        let mut sources = Vec::with_capacity(paths.len());
+        let mut hasher = DefaultHasher::new();
+        for path in paths {
+            let path = self.resolve_path(path);
+            path.hash(&mut hasher);
+            self.tracked_files.push(path.clone());
+            let image = image::load_png(&path)?;
+            if image.width != image.height {
+                return Err(Error::InvalidIcon {
+                    path,
+                    message: format!("the image is not square, but {}x{}", image.width, image.height),
+                });
             }
+            sources.push(image);
         }
-        try!(writeln!(f, "}}\n}}"));
+        sources.sort_by_key(|i| i.width);
+        let largest = match sources.last() {
+            Some(image) => image.width,
+            None => return Err(Error::Unsupported("An icon needs at least one PNG file".to_string())),
+        };
+        let mut sizes: Vec<u32> = ICON_SIZES.iter().cloned().filter(|&s| s <= largest).collect();
+        sizes.extend(sources.iter().map(|i| i.width).filter(|&w| w <= 256));
+        sizes.sort_unstable();
+        sizes.dedup();
+        let images: Vec<image::Image> = sizes.iter()
+            .map(|&size| {
+                // there is always a source that is large enough
+                let source = sources.iter().find(|i| i.width >= size).unwrap();
+                if source.width == size { source.clone() } else { source.resize(size, size) }
+            })
+            .collect();
 
-        try!(writeln!(f, "BLOCK \"VarFileInfo\" {{"));
-        try!(writeln!(f, "VALUE \"Translation\", {:#x}, 0x04b0", self.language));
-        try!(writeln!(f, "}}\n}}"));
-        if self.icon.is_some() {
-            try!(writeln!(f, "1 ICON \"{}\"", self.icon.as_ref().unwrap()));
-        }
-        if let Some(e) = self.version_info.get(&VersionInfo::FILETYPE) {
-            if let Some(manf) = self.manifest.as_ref() {
-                try!(writeln!(f, "{} 24", e));
-                try!(writeln!(f, "{{"));
-                for line in manf.lines() {
-                    try!(writeln!(f, "\"{}\"", line.replace("\"", "\"\"").trim()));
-                }
-                try!(writeln!(f, "}}"));
-            } else if let Some(manf) = self.manifest_file.as_ref() {
-                try!(writeln!(f, "{} 24 \"{}\"", e, manf));
-            }
-        }
-        Ok(())
+        let name = Path::new(paths[0]).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let icon = PathBuf::from(&self.output_directory).join(format!("{}-{:016x}.ico", name, hasher.finish()));
+        ico::write_icon(&mut fs::File::create(&icon)?, &images)?;
+        Ok(self.set_icon(&icon.to_string_lossy()))
     }
 
-    /// Set a path to an already existing resource file.
+    /// Add another icon, e.g., for a file type or the notification area
     ///
-    /// We will neither modify this file nor parse its contents. This function
-    /// simply replaces the internaly generated resource file that is passed to
-    /// the compiler. You can use this function to write a resource file yourself.
-    pub fn set_resource_file<'a>(&mut self, path: &'a str) -> &mut Self {
-        self.rc_file = Some(path.to_string());
-        self
-    }
+    /// The id is a number or a name, it is used to load the icon with `LoadIcon()`.
+    /// Names are case insensitive. Adding an icon with the same id again replaces it.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("app.ico")
+    ///    .add_icon(2, "document.ico")
+    ///    .add_icon(3, "tray.ico");
+    /// ```
+    ///
+    /// Names come before numbers, so a named icon would take the place of the main icon
+    /// with id `1` and compiling fails. With named icons, the main icon needs a name as well,
+    /// one that comes first, see [`set_icon_with_id()`]:
+    ///
+    /// ```rust
+    /// let out = std::env::temp_dir().join("named_icons.res");
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("test.ico")
+    ///    .add_icon("tray", "test.ico");
+    /// assert!(res.write_res_file(&out).is_err());
+    ///
+    /// res.set_icon_with_id("app", "test.ico");
+    /// assert!(res.write_res_file(&out).is_ok());
+    /// ```
+    ///
+    /// [`set_icon_with_id()`]: #method.set_icon_with_id
+    pub fn add_icon<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.icons.insert(id.into(), path.to_string());
+        self
+    }
+
+    /// Read an icon, list its images and warn about problems
+    fn read_icon(&self, icon: &str) -> Result<Vec<ico::IconImage>> {
+        let path = self.resolve_path(icon);
+        let images = ico::read_icon(&path)?;
+        let formats: Vec<String> = images.iter()
+            .map(|i| format!("{}x{} {} bits{}", i.format.width, i.format.height, i.format.bit_count,
+                             if i.format.png { " PNG" } else { "" }))
+            .collect();
+        println!("{}: {}", path.display(), formats.join(", "));
+        for warning in ico::icon_warnings(&images) {
+            println!("cargo:warning={}: {}", path.display(), warning);
+        }
+        Ok(images)
+    }
+
+    /// Add a cursor
+    ///
+    /// The cursor has to be in `cur` format, the hotspot of its images is kept. The id is a
+    /// number or a name, it is used to load the cursor with `LoadCursor()`.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_cursor("pencil", "cursors/pencil.cur")
+    ///    .add_animated_cursor("busy", "cursors/busy.ani");
+    /// ```
+    pub fn add_cursor<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.add_file(FileKind::Cursor, id.into(), path)
+    }
+
+    /// Add an animated cursor (`ANICURSOR`) from an `ani` file
+    pub fn add_animated_cursor<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.add_file(FileKind::AnimatedCursor, id.into(), path)
+    }
+
+    /// Add an animated icon (`ANIICON`) from an `ani` file
+    pub fn add_animated_icon<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.add_file(FileKind::AnimatedIcon, id.into(), path)
+    }
+
+    /// Add a bitmap (`BITMAP`) from a `bmp` file
+    ///
+    /// The id is a number or a name, it is used to load the bitmap with `LoadBitmap()` or
+    /// `LoadImage()`, e.g., for toolbars or splash screens. The file is checked before it is
+    /// compiled. If the pixels do not follow the color table directly, as `LoadBitmap()`
+    /// requires, the bitmap is rewritten without the gap.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_bitmap(101, "images/toolbar.bmp")
+    ///    .add_bitmap("splash", "images/splash.bmp");
+    /// ```
+    pub fn add_bitmap<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.add_file(FileKind::Bitmap, id.into(), path)
+    }
+
+    /// Embed a file unchanged as `RCDATA` resource
+    ///
+    /// The id is a number or a name. At runtime, the data is found with `FindResource()`
+    /// and the type `RT_RCDATA`, e.g., default settings, a database or license texts.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_rcdata("defaults", "data/defaults.toml")
+    ///    .add_rcdata(2, "LICENSE");
+    /// ```
+    pub fn add_rcdata<I: Into<ResourceId>>(&mut self, id: I, path: &str) -> &mut Self {
+        self.add_custom_resource(res::RT_RCDATA, id, path)
+    }
+
+    /// Embed a file or bytes as resource of any type
+    ///
+    /// Type and id are numbers or names. Numbered types below 256 are reserved for the
+    /// types Windows defines, so a number from 256 on or a name is the usual choice. A
+    /// resource of the same type and id is replaced.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_custom_resource("config", 1, "data/config.json")
+    ///    .add_custom_resource(256, "build", &b"release"[..]);
+    /// ```
+    pub fn add_custom_resource<T, I, D>(&mut self, res_type: T, id: I, data: D) -> &mut Self
+        where T: Into<ResourceId>,
+              I: Into<ResourceId>,
+              D: Into<ResourceData>
+    {
+        let (res_type, id) = (res_type.into(), id.into());
+        self.custom.retain(|c| c.0 != res_type || c.1 != id);
+        self.custom.push((res_type, id, data.into()));
+        self
+    }
+
+    /// Check the types and ids of the custom resources
+    ///
+    /// A named type must not be a keyword of resource scripts, the resource compilers would
+    /// take it for the predefined type.
+    fn check_custom(&self) -> Result<()> {
+        for (res_type, id, _) in &self.custom {
+            res_type.check()
+                .and_then(|_| match *res_type {
+                    ResourceId::Name(ref name) if RC_TYPE_KEYWORDS.contains(&name.as_str()) => {
+                        Err(format!("the type {} is a keyword of resource scripts, use a number instead", name))
+                    }
+                    _ => Ok(()),
+                })
+                .and_then(|_| id.check())
+                .map_err(|message| Error::InvalidResource(format!("{} {}: {}", res_type, id, message)))?;
+        }
+        Ok(())
+    }
+
+    /// Set the message table, e.g., for the messages of an Event Log source
+    ///
+    /// The table is embedded as `RT_MESSAGETABLE` resource with id 1, once for every language,
+    /// where texts without language have the language of [`set_language()`]. `compile()`
+    /// also writes the message ids as constants to `messages.rs` in the output directory.
+    /// See [`MessageTable`] for an example.
+    ///
+    /// [`set_language()`]: #method.set_language
+    /// [`MessageTable`]: struct.MessageTable.html
+    pub fn set_message_table(&mut self, messages: &MessageTable) -> &mut Self {
+        self.messages = Some(messages.clone());
+        self
+    }
+
+    /// Check the names and texts of the message table
+    fn check_messages(&self) -> Result<()> {
+        match self.messages {
+            Some(ref messages) => messages.check().map_err(|message| Error::InvalidResource(format!("message table: {}", message))),
+            None => Ok(()),
+        }
+    }
+
+    /// Read the data of a custom resource
+    fn read_custom(&self, data: &ResourceData) -> Result<Vec<u8>> {
+        match *data {
+            ResourceData::File(ref path) => {
+                fs::read(self.resolve_path(path)).map_err(|e| Error::InvalidResource(format!("can not read {}: {}", path, e)))
+            }
+            ResourceData::Bytes(ref bytes) => Ok(bytes.clone()),
+        }
+    }
+
+    /// Tell cargo about every file the resource is built from
+    ///
+    /// With tracking, `compile()` prints `cargo:rerun-if-changed` for `Cargo.toml`, the icons,
+    /// bitmaps, cursors, data files, the message file, the manifest file and the resource file.
+    /// Cargo then runs the build script again whenever one of them changes, including files
+    /// outside of the package, but **no longer** after any other change of the package. Files a
+    /// resource file given with [`set_resource_file()`] includes are not known to us, so they
+    /// would not trigger a new run. Without tracking, the default, cargo runs the build script
+    /// after every change of the package.
+    ///
+    /// [`set_resource_file()`]: #method.set_resource_file
+    pub fn track_files(&mut self, track: bool) -> &mut Self {
+        self.track_files = track;
+        self
+    }
+
+    /// Print `cargo:rerun-if-changed` for every file the resource is built from, if enabled
+    fn rerun_if_changed(&self) {
+        if !self.track_files {
+            return;
+        }
+        let mut paths = self.tracked_files.clone();
+        paths.extend(self.icons.values().map(|p| self.resolve_path(p)));
+        paths.extend(self.files.iter().map(|f| self.resolve_path(&f.2)));
+        for c in &self.custom {
+            if let ResourceData::File(ref path) = c.2 {
+                paths.push(self.resolve_path(path));
+            }
+        }
+        paths.extend(self.messages.as_ref().and_then(|m| m.source()).map(|p| p.to_path_buf()));
+        paths.extend(self.manifest_file.iter().chain(self.rc_file.iter()).map(|p| self.resolve_path(p)));
+        // files we generate, e.g., icons from PNG files, would trigger a new run after every build
+        for path in paths.iter().filter(|p| !p.starts_with(&self.output_directory)) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    /// Add a resource embedded from a file, replacing one of the same kind and id
+    fn add_file(&mut self, kind: FileKind, id: ResourceId, path: &str) -> &mut Self {
+        self.files.retain(|f| f.0 != kind || f.1 != id);
+        self.files.push((kind, id, path.to_string()));
+        self
+    }
+
+    /// Check a file resource and read its data
+    ///
+    /// For cursors, the images are returned, the resources are built from them later.
+    /// For bitmaps, the data is the bitmap without its file header.
+    fn read_file(&self, kind: FileKind, path: &str) -> Result<FileData> {
+        let path = self.resolve_path(path);
+        match kind {
+            FileKind::Cursor => Ok(FileData::Images(ico::read_cursor(&path)?)),
+            FileKind::AnimatedCursor | FileKind::AnimatedIcon => Ok(FileData::Raw(ico::read_animated(&path)?)),
+            FileKind::Bitmap => Ok(FileData::Raw(bmp::read_bitmap(&path)?.dib)),
+        }
+    }
+
+    /// Get the path of a bitmap for the resource compiler
+    ///
+    /// The resource compilers only strip the file header, a bitmap with a gap before the
+    /// pixels is written to the output directory without the gap first. The file name
+    /// contains the resource id, as several bitmaps may have the same file name.
+    fn packed_bitmap(&self, id: &ResourceId, path: &str) -> Result<String> {
+        let bitmap = bmp::read_bitmap(self.resolve_path(path))?;
+        if !bitmap.repacked {
+            return Ok(path.to_string());
+        }
+        let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("bitmap");
+        let packed = PathBuf::from(&self.output_directory).join(format!("{}-{}-packed.bmp", stem, id));
+        fs::write(&packed, bitmap.to_file())?;
+        println!("{}: removed the gap before the pixels, using {}", path, packed.display());
+        Ok(packed.to_string_lossy().into_owned())
+    }
+
+    /// Check the resource ids of the embedded files
+    fn check_files(&self) -> Result<()> {
+        for &(kind, ref id, ref path) in &self.files {
+            id.check().map_err(|message| {
+                let path = PathBuf::from(path);
+                match kind {
+                    FileKind::Bitmap => Error::InvalidBitmap { path, message },
+                    _ => Error::InvalidIcon { path, message },
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Check the icon ids, and that the main icon is the first one
+    fn check_icons(&self) -> Result<()> {
+        for (id, path) in &self.icons {
+            id.check().map_err(|message| Error::InvalidIcon { path: PathBuf::from(path), message })?;
+        }
+        if let (Some(main), Some((first, path))) = (self.main_icon.as_ref(), self.icons.iter().next()) {
+            if main != first {
+                return Err(Error::InvalidIcon {
+                    path: PathBuf::from(path),
+                    message: format!("its id {} comes before the id {} of the main icon, Explorer would show it instead",
+                                     first, main),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Set a version info struct property
+    /// Currently we only support numeric values, you have to look them up.
+    /// For most fields there are typed alternatives, e.g., [`set_file_flags()`].
+    ///
+    /// [`set_file_flags()`]: #method.set_file_flags
+    pub fn set_version_info(&mut self, field: VersionInfo, value: u64) -> &mut Self {
+        self.version_info.insert(field, value);
+        self
+    }
+
+    /// Choose how the fourth component of `FILEVERSION` and `PRODUCTVERSION` is derived
+    ///
+    /// By default it is `0`, see [`VersionStrategy`] for the alternatives. Both fields
+    /// are recomputed from `package.version`, overwriting values set with
+    /// [`set_version_info()`] before.
+    ///
+    /// Returns an error if the version can not be represented, i.e., a component is
+    /// larger than 65535.
+    ///
+    /// ```rust
+    /// # fn test_main() -> winres::Result<()> {
+    /// let mut res = winres::WindowsResource::new();
+    /// // with version = "1.2.3-rc.4" this yields 1.2.3.30004
+    /// res.set_version_strategy(winres::VersionStrategy::PreReleaseChannel)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`VersionStrategy`]: enum.VersionStrategy.html
+    /// [`set_version_info()`]: #method.set_version_info
+    pub fn set_version_strategy(&mut self, strategy: VersionStrategy) -> Result<&mut Self> {
+        let version = cargo_version(strategy)?;
+        self.version_info.insert(VersionInfo::FILEVERSION, version);
+        self.version_info.insert(VersionInfo::PRODUCTVERSION, version);
+        Ok(self)
+    }
+
+    /// Set the `FILEFLAGS` of the version info
+    ///
+    /// If [`FileFlags::PRIVATEBUILD`] or [`FileFlags::SPECIALBUILD`] is set, the
+    /// `"PrivateBuild"` or `"SpecialBuild"` string has to be set as well.
+    ///
+    /// ```rust
+    /// use winres::FileFlags;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_file_flags(FileFlags::PRERELEASE | FileFlags::PRIVATEBUILD)
+    ///    .set("PrivateBuild", "Built by Jane on her laptop");
+    /// ```
+    ///
+    /// [`FileFlags::PRIVATEBUILD`]: struct.FileFlags.html#associatedconstant.PRIVATEBUILD
+    /// [`FileFlags::SPECIALBUILD`]: struct.FileFlags.html#associatedconstant.SPECIALBUILD
+    pub fn set_file_flags(&mut self, flags: FileFlags) -> &mut Self {
+        self.version_info.insert(VersionInfo::FILEFLAGS, flags.bits() as u64);
+        self
+    }
+
+    /// Set the `FILEOS` of the version info
+    pub fn set_file_os(&mut self, os: FileOs) -> &mut Self {
+        self.version_info.insert(VersionInfo::FILEOS, os.value() as u64);
+        self
+    }
+
+    /// Set the `FILETYPE` of the version info
+    ///
+    /// This resets `FILESUBTYPE`, which only drivers, fonts and virtual devices have.
+    /// Set it with [`set_file_subtype()`] afterwards.
+    ///
+    /// [`set_file_subtype()`]: #method.set_file_subtype
+    pub fn set_file_type(&mut self, file_type: FileType) -> &mut Self {
+        self.version_info.insert(VersionInfo::FILETYPE, file_type.value() as u64);
+        self.version_info.insert(VersionInfo::FILESUBTYPE, 0);
+        self
+    }
+
+    /// Set the `FILESUBTYPE` of the version info
+    ///
+    /// The subtype has to belong to the file type set before.
+    ///
+    /// ```rust
+    /// use winres::{FileSubtype, FileType};
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_file_type(FileType::FONT);
+    /// assert!(res.set_file_subtype(FileSubtype::FONT_TRUETYPE).is_ok());
+    /// assert!(res.set_file_subtype(FileSubtype::DRV_PRINTER).is_err());
+    /// ```
+    pub fn set_file_subtype(&mut self, subtype: FileSubtype) -> Result<&mut Self> {
+        let file_type = *self.version_info.get(&VersionInfo::FILETYPE).unwrap_or(&0);
+        if let Some(t) = subtype.file_type() {
+            if t.value() as u64 != file_type {
+                return Err(Error::InvalidVersionInfo(format!("{:?} is not a subtype of FILETYPE {:#x}", subtype, file_type)));
+            }
+        }
+        self.version_info.insert(VersionInfo::FILESUBTYPE, subtype.value() as u64);
+        Ok(self)
+    }
+
+    /// Check that the version info fields fit together
+    fn check_version_info(&self) -> Result<()> {
+        let get = |k: VersionInfo| *self.version_info.get(&k).unwrap_or(&0);
+        version::check(get(VersionInfo::FILETYPE),
+                       get(VersionInfo::FILESUBTYPE),
+                       get(VersionInfo::FILEFLAGS),
+                       get(VersionInfo::FILEFLAGSMASK)).map_err(Error::InvalidVersionInfo)?;
+        let flags = get(VersionInfo::FILEFLAGS) as u32;
+        for &(flag, name) in &[(FileFlags::PRIVATEBUILD, "PrivateBuild"), (FileFlags::SPECIALBUILD, "SpecialBuild")] {
+            let set = self.string_tables().iter().all(|t| matches!(t.1.get(name), Some(v) if !v.is_empty()));
+            if flags & flag.bits() != 0 && !set {
+                return Err(Error::InvalidVersionInfo(format!("FILEFLAGS contains {:?}, but \"{}\" is not set", flag, name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the embedded manifest file
+    ///
+    /// # Example
+    ///
+    /// The following manifest will brand the exe as requesting administrator privileges.
+    /// Thus everytime it is executed, a Windows UAC dialog will appear.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_manifest(r#"
+    /// <assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+    /// <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    ///     <security>
+    ///         <requestedPrivileges>
+    ///             <requestedExecutionLevel level="requireAdministrator" uiAccess="false" />
+    ///         </requestedPrivileges>
+    ///     </security>
+    /// </trustInfo>
+    /// </assembly>
+    /// "#);
+    /// ```
+    ///
+    /// The manifest is checked before it is embedded: it has to be well-formed XML, and the
+    /// elements, attributes and namespaces Windows reads have to be spelled correctly.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_manifest(r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+    /// <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3"><security><requestedPrivileges>
+    /// <requestedExecutionLevel level="requireAdmin"/>
+    /// </requestedPrivileges></security></trustInfo>
+    /// </assembly>"#);
+    /// let e = res.write_res_file(std::env::temp_dir().join("manifest.res")).unwrap_err();
+    /// assert!(e.to_string().contains("line 3, column 33"));
+    /// ```
+    pub fn set_manifest(&mut self, manifest: &str) -> &mut Self {
+        self.manifest_file = None;
+        self.app_manifest = None;
+        self.manifest = Some(manifest.to_string());
+        self
+    }
+
+    /// Some as [`set_manifest()`] but a filename can be provided and
+    /// file is included by the resource compieler itself.
+    /// This method works the same way as [`set_icon()`]
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_icon()`]: #method.set_icon
+    pub fn set_manifest_file(&mut self, file: &str) -> &mut Self {
+        self.manifest_file = Some(file.to_string());
+        self.manifest = None;
+        self.app_manifest = None;
+        self
+    }
+
+    /// Same as [`set_manifest()`], but the manifest is built with [`Manifest`]
+    ///
+    /// The `assemblyIdentity` defaults to the name of the package and the `FILEVERSION`.
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`Manifest`]: struct.Manifest.html
+    pub fn set_application_manifest(&mut self, manifest: &Manifest) -> &mut Self {
+        self.app_manifest = Some(manifest.clone());
+        self.manifest = None;
+        self.manifest_file = None;
+        self
+    }
+
+    /// Check that the manifest is well-formed and has only known elements and attributes
+    ///
+    /// The error has the line and column, Windows would only refuse to start the executable.
+    fn check_manifest(&self) -> Result<()> {
+        let (source, xml) = match self.manifest_xml() {
+            Some(xml) => (None, xml),
+            None => {
+                let file = match self.manifest_file {
+                    Some(ref file) => file,
+                    None => return Ok(()),
+                };
+                let buf = fs::read(self.resolve_path(file))?;
+                let xml = manifest::decode(&buf)
+                    .ok_or_else(|| Error::InvalidManifest(format!("{} is neither valid UTF-8 nor UTF-16", file)))?;
+                (Some(file), xml)
+            }
+        };
+        manifest::check_xml(&xml).map_err(|(pos, message)| {
+            Error::InvalidManifest(match source {
+                Some(file) => format!("{}:{}:{}: {}", file, pos.row, pos.col, message),
+                None => format!("line {}, column {}: {}", pos.row, pos.col, message),
+            })
+        })
+    }
+
+    /// The manifest XML given as string or built with `Manifest`
+    fn manifest_xml(&self) -> Option<String> {
+        match self.app_manifest {
+            Some(ref manifest) => {
+                let name = env::var("CARGO_PKG_NAME").ok();
+                let version = *self.version_info.get(&VersionInfo::FILEVERSION).unwrap_or(&0);
+                Some(manifest.to_xml(name.as_ref().map(|n| (n.as_str(), version))))
+            }
+            None => self.manifest.clone(),
+        }
+    }
+
+    /// Write a resource file with the set values
+    pub fn write_resource_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.check_version_info()?;
+        self.check_icons()?;
+        self.check_files()?;
+        self.check_strings()?;
+        self.check_dialogs()?;
+        self.check_menus()?;
+        self.check_custom()?;
+        self.check_messages()?;
+        self.check_manifest()?;
+        let mut f = fs::File::create(path)?;
+        // we don't need to include this, we use constants instead of macro names
+        // write!(f, "#include <winver.h>\n")?;
+
+        // use UTF8 as an encoding
+        // this makes it easier, since in rust all string are UTF8
+        writeln!(f, "#pragma code_page(65001)")?;
+        // the language of every resource, as in the binary writer
+        writeln!(f, "LANGUAGE {:#x}, {:#x}", self.language & 0x3FF, self.language >> 10)?;
+        writeln!(f, "1 VERSIONINFO")?;
+        for (k, v) in self.version_info.iter() {
+            match *k {
+                VersionInfo::FILEVERSION |
+                VersionInfo::PRODUCTVERSION => {
+                    writeln!(f,
+                             "{:?} {}, {}, {}, {}",
+                             k,
+                             (*v >> 48) as u16,
+                             (*v >> 32) as u16,
+                             (*v >> 16) as u16,
+                             *v as u16)?
+                }
+                _ => writeln!(f, "{:?} {:#x}", k, v)?,
+            };
+        }
+        let tables = self.string_tables();
+        writeln!(f, "{{\nBLOCK \"StringFileInfo\"\n{{")?;
+        for &(language, ref props) in &tables {
+            writeln!(f, "BLOCK \"{:04x}04b0\"\n{{", language)?;
+            for (k, v) in props.iter() {
+                if !v.is_empty() {
+                    writeln!(f, "VALUE \"{}\", \"{}\"", rc_string(k), rc_string(v))?;
+                }
+            }
+            writeln!(f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+
+        writeln!(f, "BLOCK \"VarFileInfo\" {{")?;
+        write!(f, "VALUE \"Translation\"")?;
+        for &(language, _) in &tables {
+            write!(f, ", {:#x}, 0x04b0", language)?;
+        }
+        writeln!(f, "\n}}\n}}")?;
+        for (id, icon) in &self.icons {
+            // fail here with a clear message, instead of in the resource compiler
+            self.read_icon(icon)?;
+            writeln!(f, "{} ICON \"{}\"", id, icon)?;
+        }
+        for &(kind, ref id, ref file) in &self.files {
+            let file = if kind == FileKind::Bitmap {
+                self.packed_bitmap(id, file)?
+            } else {
+                self.read_file(kind, file)?;
+                file.clone()
+            };
+            writeln!(f, "{} {} \"{}\"", id, kind.rc_type(), file)?;
+        }
+        for (language, strings) in self.string_table_languages() {
+            writeln!(f, "STRINGTABLE\nLANGUAGE {:#x}, {:#x}\n{{", language & 0x3FF, language >> 10)?;
+            for (id, text) in strings {
+                writeln!(f, "{}, \"{}\"", id, rc_string(&text))?;
+            }
+            writeln!(f, "}}")?;
+        }
+        for (id, dialog) in &self.dialogs {
+            dialog.write_rc(&mut f, id, self.language)?;
+        }
+        for (id, menu) in &self.menus {
+            menu.write_rc(&mut f, id)?;
+        }
+        for (id, accelerators) in &self.accelerators {
+            accelerators.write_rc(&mut f, id, self.language)?;
+        }
+        for (res_type, id, data) in &self.custom {
+            let path = match *data {
+                ResourceData::File(ref path) => {
+                    // fail here with a clear message, instead of in the resource compiler
+                    self.read_custom(data)?;
+                    path.clone()
+                }
+                ResourceData::Bytes(ref bytes) => {
+                    // the resource compilers can only embed bytes from a file
+                    let mut hasher = DefaultHasher::new();
+                    bytes.hash(&mut hasher);
+                    let path = PathBuf::from(&self.output_directory).join(format!("data-{:016x}.bin", hasher.finish()));
+                    fs::write(&path, bytes)?;
+                    path.to_string_lossy().into_owned()
+                }
+            };
+            match *res_type {
+                ResourceId::Ordinal(res::RT_RCDATA) => writeln!(f, "{} RCDATA \"{}\"", id, path)?,
+                ref res_type => writeln!(f, "{} {} \"{}\"", id, res_type, path)?,
+            }
+        }
+        if let Some(ref messages) = self.messages {
+            // the resource compilers need mc.exe's binary files, MESSAGETABLE is not a keyword for all of them
+            for (language, data) in messages.resources(self.language) {
+                let path = PathBuf::from(&self.output_directory).join(format!("messages-{:04x}.bin", language));
+                fs::write(&path, data)?;
+                writeln!(f, "LANGUAGE {:#x}, {:#x}", language & 0x3FF, language >> 10)?;
+                writeln!(f, "1 {} \"{}\"", res::RT_MESSAGETABLE, path.display())?;
+            }
+            writeln!(f, "LANGUAGE {:#x}, {:#x}", self.language & 0x3FF, self.language >> 10)?;
+        }
+        if let Some(e) = self.version_info.get(&VersionInfo::FILETYPE) {
+            if let Some(manf) = self.manifest_xml() {
+                writeln!(f, "{} 24", e)?;
+                writeln!(f, "{{")?;
+                for line in manf.lines() {
+                    writeln!(f, "\"{}\"", line.replace("\"", "\"\"").trim())?;
+                }
+                writeln!(f, "}}")?;
+            } else if let Some(manf) = self.manifest_file.as_ref() {
+                writeln!(f, "{} 24 \"{}\"", e, manf)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a binary resource file (`.res`) with the set values
+    ///
+    /// This is the same format the resource compilers produce, i.e., the resource
+    /// is compiled without invoking `rc.exe` or `windres.exe`. The file can be passed
+    /// directly to the MSVC linker.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("InternalName", "TEST.EXE")
+    ///    .set_icon("test.ico");
+    /// res.write_res_file(std::env::temp_dir().join("resource.res")).unwrap();
+    /// ```
+    pub fn write_res_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let resources = self.resources()?;
+        let mut f = io::BufWriter::new(fs::File::create(path)?);
+        res::write_res(&mut f, &resources)?;
+        Ok(f.flush()?)
+    }
+
+    /// Write an object file with the set values
+    ///
+    /// The object file contains the `.rsrc` section for the architecture of the
+    /// compilation target (x86, x86_64 or aarch64). This is the same as compiling the
+    /// resource with `windres.exe` or converting it with `cvtres.exe`.
+    pub fn write_object_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let machine = coff::Machine::from_arch(&target_arch())
+            .ok_or_else(|| Error::Unsupported(format!("Can not write object files for {}", target_arch())))?;
+        let resources = self.resources()?;
+        let mut f = io::BufWriter::new(fs::File::create(path)?);
+        coff::write_object(&mut f, machine, &resources)?;
+        Ok(f.flush()?)
+    }
+
+    /// Collect all resources in their binary form
+    fn resources(&self) -> Result<Vec<Resource>> {
+        self.check_version_info()?;
+        self.check_icons()?;
+        self.check_files()?;
+        self.check_strings()?;
+        self.check_dialogs()?;
+        self.check_menus()?;
+        self.check_custom()?;
+        self.check_messages()?;
+        self.check_manifest()?;
+        let mut resources = Vec::new();
+        resources.push(Resource::new(ResourceId::Ordinal(res::RT_VERSION),
+                                     ResourceId::Ordinal(1),
+                                     self.language,
+                                     self.version_info_data()));
+        // the images of all icons are numbered consecutively
+        let mut next_image = 1;
+        for (id, icon) in &self.icons {
+            let images = self.read_icon(icon)?;
+            let count = images.len() as u16;
+            resources.extend(ico::icon_resources(id.clone(), images, next_image, self.language));
+            next_image += count;
+        }
+        let mut next_cursor = 1;
+        for &(kind, ref id, ref file) in &self.files {
+            match self.read_file(kind, file)? {
+                FileData::Images(images) => {
+                    let count = images.len() as u16;
+                    resources.extend(ico::cursor_resources(id.clone(), images, next_cursor, self.language));
+                    next_cursor += count;
+                }
+                FileData::Raw(data) => {
+                    let res_type = match kind {
+                        FileKind::AnimatedCursor => res::RT_ANICURSOR,
+                        FileKind::AnimatedIcon => res::RT_ANIICON,
+                        _ => res::RT_BITMAP,
+                    };
+                    resources.push(Resource::new(ResourceId::Ordinal(res_type), id.clone(), self.language, data));
+                }
+            }
+        }
+        for (language, strings) in self.string_table_languages() {
+            resources.extend(res::string_resources(&strings, language));
+        }
+        for (id, dialog) in &self.dialogs {
+            let mut r = Resource::new(ResourceId::Ordinal(res::RT_DIALOG), id.clone(), self.language, dialog.template());
+            r.memory_flags = 0x1030;
+            resources.push(r);
+        }
+        for (id, menu) in &self.menus {
+            let mut r = Resource::new(ResourceId::Ordinal(res::RT_MENU), id.clone(), self.language, menu.template());
+            r.memory_flags = 0x1030;
+            resources.push(r);
+        }
+        for (id, accelerators) in &self.accelerators {
+            resources.push(Resource::new(ResourceId::Ordinal(res::RT_ACCELERATOR), id.clone(), self.language, accelerators.table()));
+        }
+        for (res_type, id, data) in &self.custom {
+            resources.push(Resource::new(res_type.clone(), id.clone(), self.language, self.read_custom(data)?));
+        }
+        if let Some(ref messages) = self.messages {
+            for (language, data) in messages.resources(self.language) {
+                resources.push(Resource::new(ResourceId::Ordinal(res::RT_MESSAGETABLE), ResourceId::Ordinal(1), language, data));
+            }
+        }
+        if let Some(e) = self.version_info.get(&VersionInfo::FILETYPE) {
+            let manifest = if let Some(manf) = self.manifest_xml() {
+                Some(manf.into_bytes())
+            } else if let Some(manf) = self.manifest_file.as_ref() {
+                let mut buf = Vec::new();
+                fs::File::open(self.resolve_path(manf))?.read_to_end(&mut buf)?;
+                Some(buf)
+            } else {
+                None
+            };
+            if let Some(data) = manifest {
+                resources.push(Resource::new(ResourceId::Ordinal(res::RT_MANIFEST),
+                                             ResourceId::Ordinal(*e as u16),
+                                             self.language,
+                                             data));
+            }
+        }
+        Ok(resources)
+    }
+
+    /// Serialize the `VS_VERSIONINFO` structure
+    fn version_info_data(&self) -> Vec<u8> {
+        let get = |k: VersionInfo| *self.version_info.get(&k).unwrap_or(&0);
+        let mut fixed = Vec::with_capacity(52);
+        res::push_u32(&mut fixed, 0xFEEF04BD);
+        res::push_u32(&mut fixed, 0x00010000);
+        for v in &[get(VersionInfo::FILEVERSION), get(VersionInfo::PRODUCTVERSION)] {
+            res::push_u32(&mut fixed, (*v >> 32) as u32);
+            res::push_u32(&mut fixed, *v as u32);
+        }
+        res::push_u32(&mut fixed, get(VersionInfo::FILEFLAGSMASK) as u32);
+        res::push_u32(&mut fixed, get(VersionInfo::FILEFLAGS) as u32);
+        res::push_u32(&mut fixed, get(VersionInfo::FILEOS) as u32);
+        res::push_u32(&mut fixed, get(VersionInfo::FILETYPE) as u32);
+        res::push_u32(&mut fixed, get(VersionInfo::FILESUBTYPE) as u32);
+        // file date
+        res::push_u32(&mut fixed, 0);
+        res::push_u32(&mut fixed, 0);
+
+        let tables = self.string_tables();
+        let string_tables: Vec<Vec<u8>> = tables.iter()
+            .map(|&(language, ref props)| {
+                let strings: Vec<Vec<u8>> = props.iter()
+                    .filter(|&(_, v)| !v.is_empty())
+                    .map(|(k, v)| {
+                        let value = res::wstr(v);
+                        res::version_node(k, true, &value, (value.len() / 2) as u16, &[])
+                    })
+                    .collect();
+                res::version_node(&format!("{:04x}04b0", language), true, &[], 0, &strings)
+            })
+            .collect();
+        let string_info = res::version_node("StringFileInfo", true, &[], 0, &string_tables);
+
+        let mut translation = Vec::new();
+        for &(language, _) in &tables {
+            res::push_u16(&mut translation, language);
+            res::push_u16(&mut translation, 0x04b0);
+        }
+        let var = res::version_node("Translation", false, &translation, translation.len() as u16, &[]);
+        let var_info = res::version_node("VarFileInfo", true, &[], 0, &[var]);
+
+        res::version_node("VS_VERSION_INFO", false, &fixed, fixed.len() as u16, &[string_info, var_info])
+    }
+
+    /// Resolve a path the same way the resource compiler does, i.e.,
+    /// relative paths are relative to the project's root.
+    fn resolve_path(&self, path: &str) -> PathBuf {
+        match env::var("CARGO_MANIFEST_DIR") {
+            Ok(dir) => Path::new(&dir).join(path),
+            Err(_) => PathBuf::from(path),
+        }
+    }
+
+    /// Set a path to an already existing resource file.
+    ///
+    /// We will neither modify this file nor parse its contents. This function
+    /// simply replaces the internaly generated resource file that is passed to
+    /// the compiler. You can use this function to write a resource file yourself.
+    pub fn set_resource_file(&mut self, path: &str) -> &mut Self {
+        self.rc_file = Some(path.to_string());
+        self
+    }
+
+    /// Select the resource compiler
+    ///
+    /// As a default, the resource compiler of the toolkit is used. With
+    /// [`ResourceCompiler::Builtin`] no Windows SDK is required to build for a MSVC target,
+    /// which is useful for cross compiling.
+    ///
+    /// [`ResourceCompiler::Builtin`]: enum.ResourceCompiler.html#variant.Builtin
+    pub fn set_resource_compiler(&mut self, compiler: ResourceCompiler) -> &mut Self {
+        self.compiler = compiler;
+        self
+    }
 
     /// Override the output directoy.
     ///
     /// As a default, we use `%OUT_DIR%` set by cargo, but it can be necessary to override the
     /// the setting.
-    pub fn set_output_directory<'a>(&mut self, path: &'a str) -> &mut Self {
+    pub fn set_output_directory(&mut self, path: &str) -> &mut Self {
         self.output_directory = path.to_string();
         self
     }
 
-    #[cfg(target_env = "gnu")]
-    fn compile_with_toolkit<'a>(&self, input: &'a str, output_dir: &'a str) -> io::Result<()> {
-        let output = PathBuf::from(output_dir).join("resource.o");
-        let input = PathBuf::from(input);
-        let status = try!(process::Command::new("windres.exe")
-            .current_dir(&self.toolkit_path)
-            .arg(format!("-I{}", env::var("CARGO_MANIFEST_DIR").unwrap()))
-            .arg(format!("{}", input.display()))
-            .arg(format!("{}", output.display()))
-            .status());
-        if !status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Could not compile resource file"));
+    fn compile_with_toolkit(&self, input: &str, output_dir: &str, target: LinkTarget) -> Result<()> {
+        if self.compiler == ResourceCompiler::Llvm {
+            return self.compile_with_llvm(input, output_dir, target);
+        }
+        match WindowsResource::toolkit() {
+            Toolkit::GNU => self.compile_with_toolkit_gnu(input, output_dir, target),
+            Toolkit::MSVC => self.compile_with_toolkit_msvc(input, output_dir, target),
+            Toolkit::Unknown => {
+                Err(unsupported_toolkit())
+            }
         }
+    }
+
+    fn compile_with_toolkit_gnu(&self, input: &str, output_dir: &str, target: LinkTarget) -> Result<()> {
+        let output = PathBuf::from(output_dir).join(format!("{}.o", target.file_stem()));
+        let input = PathBuf::from(input);
+        let windres = self.find_gnu_tool("windres", &self.windres_path, "WINRES_WINDRES")?;
+        let mut cmd = process::Command::new(windres);
+        cmd.arg(format!("-I{}", cargo_env("CARGO_MANIFEST_DIR")?));
+        // windres defaults to its own architecture, which is not necessarily the target's
+        match target_arch().as_str() {
+            "x86" => cmd.arg("--target=pe-i386"),
+            "x86_64" => cmd.arg("--target=pe-x86-64"),
+            _ => &mut cmd,
+        };
+        cmd.arg(format!("{}", input.display()))
+            .arg(format!("{}", output.display()));
+        run(cmd)?;
 
+        if let LinkTarget::Bin(name) = target {
+            println!("cargo:rustc-link-arg-bin={}={}", name, output.display());
+            return Ok(());
+        }
         let libname = PathBuf::from(output_dir).join("libresource.a");
-        let status = try!(process::Command::new("ar.exe")
-            .current_dir(&self.toolkit_path)
-            .arg("rsc")
+        let ar = self.find_gnu_tool("ar", &self.ar_path, "WINRES_AR")?;
+        let mut cmd = process::Command::new(ar);
+        cmd.arg("rsc")
             .arg(format!("{}", libname.display()))
-            .arg(format!("{}", output.display()))
-            .status());
-        if !status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other,
-                                      "Could not create static library for resource file"));
-        }
+            .arg(format!("{}", output.display()));
+        run(cmd)?;
 
         println!("cargo:rustc-link-search=native={}", output_dir);
-        println!("cargo:rustc-link-lib=static={}", "resource");
+        println!("cargo:rustc-link-lib=static=resource");
 
         Ok(())
     }
 
+    /// Find a program of the GNU toolkit
+    ///
+    /// An explicitly set path takes precedence over the environment variable `var`. Otherwise
+    /// we look for the program with the MinGW target prefix, e.g., `x86_64-w64-mingw32-windres`,
+    /// and without, first in the toolkit path and then in `%PATH%`.
+    fn find_gnu_tool(&self, name: &str, path: &Option<String>, var: &str) -> Result<PathBuf> {
+        if let Some(path) = path.as_ref() {
+            return Ok(PathBuf::from(path));
+        }
+        if let Ok(path) = env::var(var) {
+            return Ok(PathBuf::from(path));
+        }
+        let arch = match target_arch().as_str() {
+            "x86" => "i686".to_string(),
+            arch => arch.to_string(),
+        };
+        let prefixed = format!("{}-w64-mingw32-{}", arch, name);
+        find_program(&prefixed, &self.toolkit_path)
+            .or_else(|| find_program(name, &self.toolkit_path))
+            .ok_or_else(|| {
+                Error::ToolkitNotFound(format!("Could not find {} or {}, set {} to its location", prefixed, name, var))
+            })
+    }
+
     /// Run the resource compiler
     ///
     /// This function generates a resource file from the settings, or
@@ -464,60 +1959,321 @@ impl WindowsResource {
     /// Further more we will print the correct statements for
     /// `cargo:rustc-link-lib=` and `cargo:rustc-link-search` on the console,
     /// so that the cargo build script can link the compiled resource file.
-    pub fn compile(&self) -> io::Result<()> {
+    pub fn compile(&self) -> Result<()> {
+        self.compile_for(LinkTarget::Package)
+    }
+
+    /// Run the resource compiler for a single binary of the package
+    ///
+    /// The same as [`compile()`], except that the resource is only linked into the binary
+    /// `name`. Other binaries and the library of the package are not affected, so every
+    /// binary of a package can get its own icon and version info. The settings of a
+    /// `package.metadata.winres.bin.NAME` table in `Cargo.toml` are applied on top of the
+    /// package settings.
+    ///
+    /// ```rust
+    /// # extern crate winres;
+    /// # fn test_main() -> winres::Result<()> {
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("server.ico")
+    ///    .set("FileDescription", "The server");
+    /// res.compile_for_bin("server")?;
+    /// res.set_icon("client.ico")
+    ///    .set("FileDescription", "The client");
+    /// res.compile_for_bin("client")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ```toml
+    /// [package.metadata.winres.bin.client]
+    /// icon = "client.ico"
+    /// FileDescription = "The client"
+    /// ```
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn compile_for_bin(&self, name: &str) -> Result<()> {
+        match self.bin_metadata.get(name) {
+            Some((metadata, dir)) => {
+                let mut res = self.clone();
+                res.apply_metadata(metadata, dir)?;
+                res.compile_for(LinkTarget::Bin(name))
+            }
+            None => self.compile_for(LinkTarget::Bin(name)),
+        }
+    }
+
+    fn compile_for(&self, target: LinkTarget) -> Result<()> {
+        self.rerun_if_changed();
         let output = PathBuf::from(&self.output_directory);
-        let rc = output.join("resource.rc");
+        if let Some(ref messages) = self.messages {
+            messages.write_constants(output.join("messages.rs"))?;
+        }
+        if self.compiler == ResourceCompiler::Builtin {
+            return self.compile_builtin(&output, target);
+        }
+        let rc = output.join(format!("{}.rc", target.file_stem()));
         if self.rc_file.is_none() {
-            try!(self.write_resource_file(&rc));
+            self.write_resource_file(&rc)?;
         }
         let rc = if let Some(s) = self.rc_file.as_ref() {
             s.clone()
         } else {
             rc.to_str().unwrap().to_string()
         };
-        try!(self.compile_with_toolkit(rc.as_str(), &self.output_directory));
+        self.compile_with_toolkit(rc.as_str(), &self.output_directory, target)?;
 
         Ok(())
     }
 
-    #[cfg(target_env = "msvc")]
-    fn compile_with_toolkit<'a>(&self, input: &'a str, output_dir: &'a str) -> io::Result<()> {
-        let rc_exe = if cfg!(target_arch = "x86_64") {
-            PathBuf::from(&self.toolkit_path).join("bin\\10.0.15063.0\\x64\\rc.exe")
+    fn compile_builtin(&self, output: &Path, target: LinkTarget) -> Result<()> {
+        if self.rc_file.is_some() {
+            return Err(Error::Unsupported("The builtin resource compiler cannot compile a resource file".to_string()));
+        }
+        match WindowsResource::toolkit() {
+            Toolkit::GNU => {
+                let object = output.join(format!("{}.o", target.file_stem()));
+                self.write_object_file(&object)?;
+                link_object(&object, &self.output_directory, target)
+            }
+            Toolkit::MSVC => {
+                let res = output.join(res_file_name(target));
+                self.write_res_file(&res)?;
+                link_res(&res, &self.output_directory, target);
+                Ok(())
+            }
+            Toolkit::Unknown => {
+                Err(unsupported_toolkit())
+            }
+        }
+    }
+
+    fn compile_with_llvm(&self, input: &str, output_dir: &str, target: LinkTarget) -> Result<()> {
+        let include = format!("-I{}", cargo_env("CARGO_MANIFEST_DIR")?);
+        // our own resource file is UTF-8, llvm-rc does not take the pragma into account
+        let toolkit = WindowsResource::toolkit();
+        if toolkit == Toolkit::GNU {
+            let output = PathBuf::from(output_dir).join(format!("{}.o", target.file_stem()));
+            if let Some(windres) = find_program("llvm-windres", &self.toolkit_path) {
+                let triple = env::var("TARGET").unwrap_or_else(|_| {
+                    match target_arch().as_str() {
+                        "x86" => "i686-pc-windows-gnu".to_string(),
+                        arch => format!("{}-pc-windows-gnu", arch),
+                    }
+                });
+                let mut cmd = process::Command::new(windres);
+                cmd.arg(include)
+                    .arg("--codepage=65001")
+                    .arg(format!("--target={}", triple))
+                    .arg(input)
+                    .arg(&output);
+                run(cmd)?;
+            } else {
+                let res = PathBuf::from(output_dir).join(format!("{}.res", target.file_stem()));
+                self.compile_with_llvm_rc(input, &include, &res)?;
+                let machine = match target_arch().as_str() {
+                    "x86" => "x86",
+                    "x86_64" => "x64",
+                    "aarch64" => "arm64",
+                    "arm" => "arm",
+                    arch => return Err(Error::Unsupported(format!("Can not convert resource files for {}", arch))),
+                };
+                let cvtres = find_program("llvm-cvtres", &self.toolkit_path)
+                    .ok_or_else(|| Error::ToolkitNotFound("Could not find llvm-windres or llvm-cvtres".to_string()))?;
+                let mut cmd = process::Command::new(cvtres);
+                cmd.arg(format!("/machine:{}", machine))
+                    .arg(format!("/out:{}", output.display()))
+                    .arg(&res);
+                run(cmd)?;
+            }
+            link_object(&output, output_dir, target)
+        } else if toolkit == Toolkit::MSVC {
+            let output = PathBuf::from(output_dir).join(res_file_name(target));
+            self.compile_with_llvm_rc(input, &include, &output)?;
+            link_res(&output, output_dir, target);
+            Ok(())
         } else {
-            PathBuf::from(&self.toolkit_path).join("bin\\10.0.15063.0\\x86\\rc.exe")
-        };
+            Err(unsupported_toolkit())
+        }
+    }
+
+    fn compile_with_llvm_rc(&self, input: &str, include: &str, output: &Path) -> Result<()> {
+        let llvm_rc = find_program("llvm-rc", &self.toolkit_path)
+            .ok_or_else(|| Error::ToolkitNotFound("Could not find llvm-rc".to_string()))?;
+        let mut cmd = process::Command::new(llvm_rc);
+        cmd.arg(include)
+            .args(["/C", "65001"])
+            .arg(format!("/FO{}", output.display()));
+        if self.rc_file.is_none() {
+            // our own resource file needs no preprocessor, llvm-rc would look for clang
+            cmd.arg("-no-cpp");
+        }
+        cmd.arg(input);
+        run(cmd)
+    }
+
+    fn compile_with_toolkit_msvc(&self, input: &str, output_dir: &str, target: LinkTarget) -> Result<()> {
+        let rc_exe = PathBuf::from(&self.toolkit_path).join(format!("bin\\10.0.15063.0\\{}\\rc.exe", sdk_arch()));
+        if !rc_exe.is_file() {
+            return Err(Error::ToolkitNotFound(format!("Could not find {}", rc_exe.display())));
+        }
         // let inc_win = PathBuf::from(&self.toolkit_path).join("Include\\10.0.10586.0\\um");
         // let inc_shared = PathBuf::from(&self.toolkit_path).join("Include\\10.0.10586.0\\shared");
-        let output = PathBuf::from(output_dir).join("resource.lib");
+        let output = PathBuf::from(output_dir).join(res_file_name(target));
         let input = PathBuf::from(input);
-        let status = try!(process::Command::new(rc_exe)
-            .arg(format!("/I{}", env::var("CARGO_MANIFEST_DIR").unwrap()))
+        let mut cmd = process::Command::new(rc_exe);
+        cmd.arg(format!("/I{}", cargo_env("CARGO_MANIFEST_DIR")?))
             //.arg(format!("/I{}", inc_shared.display()))
             //.arg(format!("/I{}", inc_win.display()))
             .arg("/nologo")
             .arg(format!("/fo{}", output.display()))
-            .arg(format!("{}", input.display()))
-            .status());
-        if !status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Could not compile resource file"));
-        }
+            .arg(format!("{}", input.display()));
+        run(cmd)?;
 
-        println!("cargo:rustc-link-search=native={}", output_dir);
-        println!("cargo:rustc-link-lib=dylib={}", "resource");
+        link_res(&output, output_dir, target);
         Ok(())
     }
 }
 
+impl Default for WindowsResource {
+    fn default() -> Self {
+        WindowsResource::new()
+    }
+}
+
+/// Get an environment variable set by cargo
+fn cargo_env(var: &str) -> Result<String> {
+    env::var(var).map_err(|_| Error::MissingEnvironment(var.to_string()))
+}
+
+/// The version of the package as 64-bit value for the version info
+fn cargo_version(strategy: VersionStrategy) -> Result<u64> {
+    let version = cargo_env("CARGO_PKG_VERSION")?;
+    version::parse_version(&version, strategy)
+        .map_err(|e| Error::InvalidVersion(format!("{}: {}", version, e)))
+}
+
+fn unsupported_toolkit() -> Error {
+    Error::Unsupported("Can only compile resource file when target_env is \"gnu\" or \"msvc\"".to_string())
+}
+
+/// Run a program of the toolkit
+///
+/// Its output is captured, warnings are passed on to cargo, everything else
+/// is part of the error if the program fails.
+fn run(mut cmd: process::Command) -> Result<()> {
+    let output = cmd.output()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let diagnostics: Vec<Diagnostic> = text.lines().filter_map(Diagnostic::parse).collect();
+    for d in diagnostics.iter().filter(|d| d.severity == Severity::Warning) {
+        println!("cargo:warning={}", d);
+    }
+    if !output.status.success() {
+        return Err(Error::Compiler {
+            program: cmd.get_program().to_string_lossy().into_owned(),
+            code: output.status.code(),
+            output: text,
+            diagnostics,
+        });
+    }
+    Ok(())
+}
+
+/// Get the architecture of the compilation target, see [`WindowsResource::toolkit()`]
+fn target_arch() -> String {
+    match env::var("CARGO_CFG_TARGET_ARCH") {
+        Ok(arch) => arch,
+        Err(_) => match env::var("TARGET") {
+            Ok(target) => {
+                let arch = target.split('-').next().unwrap_or("");
+                match arch {
+                    "i386" | "i586" | "i686" => "x86".to_string(),
+                    _ => arch.to_string(),
+                }
+            }
+            Err(_) => env::consts::ARCH.to_string(),
+        },
+    }
+}
+
+/// The name of the Windows SDK directory for the architecture of the compilation target
+fn sdk_arch() -> &'static str {
+    match target_arch().as_str() {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        _ => "x86",
+    }
+}
+
+/// The name of a compiled `.res` file
+///
+/// For the whole package it is named like a library, so that it can be passed with
+/// `rustc-link-lib`, the linker accepts `.res` files as input.
+fn res_file_name(target: LinkTarget) -> String {
+    match target {
+        LinkTarget::Package => "resource.lib".to_string(),
+        LinkTarget::Bin(_) => format!("{}.res", target.file_stem()),
+    }
+}
+
+/// Tell cargo to link a compiled `.res` file
+fn link_res(res: &Path, output_dir: &str, target: LinkTarget) {
+    match target {
+        LinkTarget::Package => {
+            println!("cargo:rustc-link-search=native={}", output_dir);
+            println!("cargo:rustc-link-lib=dylib=resource");
+        }
+        LinkTarget::Bin(name) => println!("cargo:rustc-link-arg-bin={}={}", name, res.display()),
+    }
+}
+
+/// Tell cargo to link an object file
+///
+/// For a single binary the object is passed to the linker directly, otherwise it is
+/// put into a static library that is linked as a whole. The `+whole-archive` modifier
+/// needs Rust 1.61, the archive has no symbols the linker would look for.
+fn link_object(object: &Path, output_dir: &str, target: LinkTarget) -> Result<()> {
+    if let LinkTarget::Bin(name) = target {
+        println!("cargo:rustc-link-arg-bin={}={}", name, object.display());
+        return Ok(());
+    }
+    let mut buf = Vec::new();
+    fs::File::open(object)?.read_to_end(&mut buf)?;
+    let mut f = fs::File::create(PathBuf::from(output_dir).join("libresource.a"))?;
+    coff::write_archive(&mut f, "resource.o", &buf)?;
+
+    println!("cargo:rustc-link-search=native={}", output_dir);
+    // nothing references the resource object, it has to be linked as a whole
+    println!("cargo:rustc-link-lib=static:+whole-archive=resource");
+    Ok(())
+}
+
+/// Look for a program in `dir` first, then in `%PATH%`
+fn find_program(name: &str, dir: &str) -> Option<PathBuf> {
+    let file = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+    let mut dirs = Vec::new();
+    if !dir.is_empty() {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(path) = env::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+    dirs.into_iter().map(|d| d.join(&file)).find(|p| p.is_file())
+}
+
 /// Find a Windows SDK
 fn get_sdk() -> io::Result<Vec<String>> {
-    let output = try!(process::Command::new("reg")
+    let output = process::Command::new("reg")
         .arg("query")
         .arg(r"HKLM\SOFTWARE\Microsoft\Windows Kits\Installed Roots")
-        .output());
+        .output()?;
 
-    let lines = try!(String::from_utf8(output.stdout)
-        .or_else(|e| Err(io::Error::new(io::ErrorKind::Other, e.description()))));
+    let lines = String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     let mut kits: Vec<String> = Vec::new();
     for line in lines.lines() {
         if line.trim().starts_with("KitsRoot") {
@@ -527,11 +2283,7 @@ fn get_sdk() -> io::Result<Vec<String>> {
                 .collect();
 
             let mut p = PathBuf::from(&kit);
-            if cfg!(target_arch = "x86_64") {
-                p.push(r"bin\10.0.15063.0\x64\rc.exe")
-            } else {
-                p.push(r"bin\10.0.15063.0\x86\rc.exe");
-            }
+            p.push(format!(r"bin\10.0.15063.0\{}\rc.exe", sdk_arch()));
 
             if p.exists() {
                 println!("{}", kit);
@@ -542,34 +2294,221 @@ fn get_sdk() -> io::Result<Vec<String>> {
     Ok(kits)
 }
 
-fn parse_cargo_toml(props: &mut HashMap<String, String>) -> io::Result<()> {
-    let cargo = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.toml");
-    let mut f = try!(fs::File::open(cargo));
-    let mut cargo_toml = String::new();
-    try!(f.read_to_string(&mut cargo_toml));
-    if let Some(ml) = toml::Parser::new(&cargo_toml).parse() {
-        if let Some(pkg) = ml.get("package") {
-            if let Some(pkg) = pkg.lookup("metadata.winres") {
-                if let Some(pkg) = pkg.as_table() {
-                    for (k, v) in pkg {
-                        // println!("{} {}", k ,v);
-                        if let Some(v) = v.as_str() {
-                            props.insert(k.clone(), v.to_string());
-                        } else {
-                            println!("package.metadata.winres.{} is not a string", k);
-                        }
-                    }
-                } else {
-                    println!("package.metadata.winres is not a table");
-                }
-            } else {
-                println!("package.metadata.winres does not exist");
+/// The sizes of icons generated from PNG files
+const ICON_SIZES: [u32; 6] = [16, 24, 32, 48, 64, 256];
+
+/// Resource types with a keyword in resource scripts
+const RC_TYPE_KEYWORDS: [&str; 14] = ["ACCELERATORS", "BITMAP", "CURSOR", "DIALOG", "DIALOGEX", "FONT", "HTML", "ICON",
+                                     "MENU", "MENUEX", "MESSAGETABLE", "RCDATA", "STRINGTABLE", "VERSIONINFO"];
+
+/// The keys of `package.metadata.winres` that are not version info strings, in the order they are applied
+const METADATA_KEYS: [&str; 20] = ["output-directory", "icon", "icons", "cursors", "bitmaps", "strings", "rcdata", "message-file", "manifest",
+                                   "manifest-file", "language", "version-strategy", "file-version", "product-version", "file-flags",
+                                   "file-os", "file-type", "file-subtype", "resource-compiler", "bin"];
+
+/// A resource id used as key, a number or a name
+fn metadata_id(key: &str) -> ResourceId {
+    match key.parse::<u16>() {
+        Ok(n) => ResourceId::Ordinal(n),
+        Err(_) => ResourceId::from(key),
+    }
+}
+
+/// Escape a string for a resource script, the resource compilers understand C escapes
+fn rc_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\"\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn metadata_str<'a>(name: &str, value: &'a toml::Value) -> Result<&'a str> {
+    value.as_str().ok_or_else(|| Error::Metadata(format!("{} is not a string", name)))
+}
+
+/// A 16-bit number, either an integer or a string with a decimal or hexadecimal (`0x`) number
+fn metadata_u16(name: &str, value: &toml::Value) -> Result<u16> {
+    let n = match *value {
+        toml::Value::Integer(n) => Some(n),
+        toml::Value::String(ref s) if s.starts_with("0x") || s.starts_with("0X") => i64::from_str_radix(&s[2..], 16).ok(),
+        toml::Value::String(ref s) => s.parse().ok(),
+        _ => None,
+    };
+    match n {
+        Some(n) if (0..=0xFFFF).contains(&n) => Ok(n as u16),
+        _ => Err(Error::Metadata(format!("{} is not a number between 0 and 65535", name))),
+    }
+}
+
+/// Build a manifest from a `manifest` table
+fn metadata_manifest(name: &str, table: &toml::value::Table) -> Result<Manifest> {
+    let mut manifest = Manifest::new();
+    for (key, value) in table {
+        let name = format!("{}.{}", name, key);
+        let flag = |value: &toml::Value| value.as_bool().ok_or_else(|| Error::Metadata(format!("{} is not a boolean", name)));
+        let unknown = |value: &str| Error::Metadata(format!("{} \"{}\" is unknown", name, value));
+        match key.as_str() {
+            "execution-level" => {
+                let level = metadata_str(&name, value)?;
+                manifest.execution_level(ExecutionLevel::from_name(level).ok_or_else(|| unknown(level))?);
+            }
+            "ui-access" => {
+                manifest.ui_access(flag(value)?);
+            }
+            "dpi-awareness" => {
+                let awareness = metadata_str(&name, value)?;
+                manifest.dpi_awareness(DpiAwareness::from_name(awareness).ok_or_else(|| unknown(awareness))?);
+            }
+            "long-path-aware" => {
+                manifest.long_path_aware(flag(value)?);
+            }
+            "utf8-code-page" => {
+                manifest.utf8_code_page(flag(value)?);
+            }
+            "supported-os" => {
+                let version = metadata_str(&name, value)?;
+                manifest.supported_os(WindowsVersion::from_name(version).ok_or_else(|| unknown(version))?);
+            }
+            "common-controls-v6" => {
+                manifest.common_controls_v6(flag(value)?);
+            }
+            "segment-heap" => {
+                manifest.segment_heap(flag(value)?);
             }
+            _ => return Err(Error::Metadata(format!("{} is unknown", name))),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Parse the `Cargo.toml` in `dir`
+fn parse_cargo_toml(dir: &Path) -> Result<toml::value::Table> {
+    let cargo = dir.join("Cargo.toml");
+    let mut f = fs::File::open(&cargo)?;
+    let mut cargo_toml = String::new();
+    f.read_to_string(&mut cargo_toml)?;
+    match cargo_toml.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => Ok(table),
+        Ok(_) => Err(Error::Metadata(format!("{} is not a table", cargo.display()))),
+        Err(e) => {
+            let msg = e.to_string();
+            let msg = match (e.line_col(), msg.rfind(" at line ")) {
+                (Some((line, col)), Some(i)) => format!("{}:{}:{}: {}", cargo.display(), line + 1, col + 1, &msg[..i]),
+                _ => format!("{}: {}", cargo.display(), msg),
+            };
+            Err(Error::Metadata(msg))
+        }
+    }
+}
+
+/// Get the `metadata.winres` table of the `package` or `workspace` section
+fn winres_table(cargo_toml: &toml::value::Table, section: &str) -> Result<Option<toml::value::Table>> {
+    match cargo_toml.get(section).and_then(|s| s.get("metadata")).and_then(|m| m.get("winres")) {
+        Some(toml::Value::Table(table)) => Ok(Some(table.clone())),
+        Some(_) => Err(Error::Metadata(format!("{}.metadata.winres is not a table", section))),
+        None => Ok(None),
+    }
+}
+
+/// Find the directory of the workspace a package belongs to
+///
+/// This is either the directory given by `package.workspace`, or, as cargo does it, the
+/// first directory upwards from the package whose `Cargo.toml` has a `workspace` section
+/// that does not exclude the package. `Cargo.toml` files that can not be parsed are skipped.
+fn find_workspace_root(dir: &Path, cargo_toml: &toml::value::Table) -> Result<Option<PathBuf>> {
+    if cargo_toml.contains_key("workspace") {
+        return Ok(Some(dir.to_path_buf()));
+    }
+    if let Some(root) = cargo_toml.get("package").and_then(|p| p.get("workspace")) {
+        let root = root.as_str().ok_or_else(|| Error::Metadata("package.workspace is not a string".to_string()))?;
+        return Ok(Some(dir.join(root)));
+    }
+    for parent in dir.ancestors().skip(1) {
+        if !parent.join("Cargo.toml").is_file() {
+            continue;
+        }
+        let workspace = match parse_cargo_toml(parent).map(|mut t| t.remove("workspace")) {
+            Ok(Some(toml::Value::Table(workspace))) => workspace,
+            _ => continue,
+        };
+        match dir.strip_prefix(parent) {
+            Ok(path) if !is_excluded(&workspace, path) => return Ok(Some(parent.to_path_buf())),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Check if a workspace excludes the package at `path`, relative to the workspace root
+///
+/// Like cargo, a package below a path of `exclude` is excluded, unless `members` lists it.
+/// Packages that are neither listed nor excluded are members if another member depends on
+/// them, otherwise cargo refuses to build them.
+fn is_excluded(workspace: &toml::value::Table, path: &Path) -> bool {
+    let paths = |key: &str| -> Vec<PathBuf> {
+        workspace.get(key).and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|p| p.as_str()).map(PathBuf::from).collect())
+            .unwrap_or_default()
+    };
+    let components = |path: &Path| -> Vec<String> {
+        path.components()
+            .filter(|c| *c != Component::CurDir)
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect()
+    };
+    let path = components(path);
+    let member = paths("members").iter().any(|pattern| {
+        let pattern = components(pattern);
+        pattern.len() == path.len() && pattern.iter().zip(&path).all(|(p, c)| glob_matches(p, c))
+    });
+    !member && paths("exclude").iter().any(|exclude| path.starts_with(&components(exclude)))
+}
+
+/// Match a file name against a pattern with `*` and `?`
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // the position after the last `*` in the pattern, and where it continues in the name
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
         } else {
-            println!("package section missing");
+            return false;
         }
-    } else {
-        println!("parsing error")
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Drop the keys of the package metadata that are set to `{ workspace = true }`
+///
+/// Their values are taken from the workspace defaults, which are applied anyway.
+fn inherit_workspace_keys(metadata: &mut toml::value::Table, defaults: &toml::value::Table) -> Result<()> {
+    let inherited: Vec<String> = metadata.iter()
+        .filter(|&(_, v)| v.get("workspace").and_then(|w| w.as_bool()) == Some(true))
+        .map(|(k, _)| k.clone())
+        .collect();
+    for key in inherited {
+        if !defaults.contains_key(&key) {
+            return Err(Error::Metadata(format!("package.metadata.winres.{} is inherited, but \
+                                                workspace.metadata.winres.{} is not set", key, key)));
+        }
+        metadata.remove(&key);
     }
     Ok(())
 }
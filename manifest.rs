@@ -0,0 +1,512 @@
+//! Application manifests
+//!
+//! A manifest built in code is rendered to the XML that would otherwise be passed to
+//! `set_manifest()`, with the namespaces each setting needs. Every manifest is checked before
+//! it is embedded: Windows refuses to start an executable with a malformed manifest, or with an
+//! unknown element in one of its namespaces, and only reports a side-by-side configuration error.
+
+use roxmltree::{Document, Node, TextPos};
+
+/// The privileges the application asks for when it is started, `requestedExecutionLevel`
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum ExecutionLevel {
+    /// The privileges of the parent process, no UAC prompt
+    AsInvoker,
+    /// Administrator privileges if the user has them, with a UAC prompt
+    HighestAvailable,
+    /// Always administrator privileges, with a UAC prompt
+    RequireAdministrator,
+}
+
+impl ExecutionLevel {
+    /// Get a value by its name in lowercase with dashes, e.g., `"require-administrator"`
+    pub(crate) fn from_name(name: &str) -> Option<ExecutionLevel> {
+        match name {
+            "as-invoker" => Some(ExecutionLevel::AsInvoker),
+            "highest-available" => Some(ExecutionLevel::HighestAvailable),
+            "require-administrator" => Some(ExecutionLevel::RequireAdministrator),
+            _ => None,
+        }
+    }
+
+    fn xml_name(self) -> &'static str {
+        match self {
+            ExecutionLevel::AsInvoker => "asInvoker",
+            ExecutionLevel::HighestAvailable => "highestAvailable",
+            ExecutionLevel::RequireAdministrator => "requireAdministrator",
+        }
+    }
+}
+
+/// How the application handles the DPI of the monitors, without it, Windows scales the
+/// windows as bitmaps
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum DpiAwareness {
+    /// Windows scales the application
+    Unaware,
+    /// The application scales to the DPI of the primary monitor at login
+    System,
+    /// The application scales to the DPI of the monitor of each window
+    PerMonitor,
+    /// Like `PerMonitor`, with dialogs and non-client areas scaled by Windows 10 1703 and later
+    PerMonitorV2,
+}
+
+impl DpiAwareness {
+    /// Get a value by its name in lowercase with dashes, e.g., `"per-monitor-v2"`
+    pub(crate) fn from_name(name: &str) -> Option<DpiAwareness> {
+        match name {
+            "unaware" => Some(DpiAwareness::Unaware),
+            "system" => Some(DpiAwareness::System),
+            "per-monitor" => Some(DpiAwareness::PerMonitor),
+            "per-monitor-v2" => Some(DpiAwareness::PerMonitorV2),
+            _ => None,
+        }
+    }
+
+    /// The values of `dpiAware`, read by Windows before 10 1607, and `dpiAwareness`
+    fn xml_values(self) -> (&'static str, &'static str) {
+        match self {
+            DpiAwareness::Unaware => ("false", "unaware"),
+            DpiAwareness::System => ("true", "system"),
+            DpiAwareness::PerMonitor => ("true/pm", "permonitor"),
+            DpiAwareness::PerMonitorV2 => ("true/pm", "permonitorv2,permonitor"),
+        }
+    }
+}
+
+/// A version of Windows the application was tested with, an entry of `supportedOS`
+///
+/// Windows turns off compatibility behaviour for the versions listed.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub enum WindowsVersion {
+    Vista,
+    Windows7,
+    Windows8,
+    Windows8_1,
+    /// Windows 10 and 11, and Windows Server 2016 and later
+    Windows10,
+}
+
+const ASM_V1: &str = "urn:schemas-microsoft-com:asm.v1";
+const ASM_V2: &str = "urn:schemas-microsoft-com:asm.v2";
+const ASM_V3: &str = "urn:schemas-microsoft-com:asm.v3";
+const COMPATIBILITY_V1: &str = "urn:schemas-microsoft-com:compatibility.v1";
+const MSIX_V1: &str = "urn:schemas-microsoft-com:msix.v1";
+const WINRT_V1: &str = "urn:schemas-microsoft-com:winrt.v1";
+/// The namespaces of the `windowsSettings` are this followed by the year and `/WindowsSettings`
+const SMI: &str = "http://schemas.microsoft.com/SMI/";
+
+const WINDOWS_VERSIONS: [(WindowsVersion, &str); 5] = [(WindowsVersion::Vista, "{e2011457-1546-43c5-a5fe-008deee3d3f0}"),
+                                                       (WindowsVersion::Windows7, "{35138b9a-5d96-4fbd-8e2d-a2440225f93a}"),
+                                                       (WindowsVersion::Windows8, "{4a2f28e3-53b9-4441-ba9c-d69d4a4a6e38}"),
+                                                       (WindowsVersion::Windows8_1, "{1f676c76-80e1-4239-95bb-83d0f6d0da78}"),
+                                                       (WindowsVersion::Windows10, "{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}")];
+
+impl WindowsVersion {
+    /// Get a value by its name in lowercase, e.g., `"windows7"` or `"windows8.1"`
+    pub(crate) fn from_name(name: &str) -> Option<WindowsVersion> {
+        match name {
+            "vista" => Some(WindowsVersion::Vista),
+            "windows7" => Some(WindowsVersion::Windows7),
+            "windows8" => Some(WindowsVersion::Windows8),
+            "windows8.1" => Some(WindowsVersion::Windows8_1),
+            "windows10" => Some(WindowsVersion::Windows10),
+            _ => None,
+        }
+    }
+}
+
+/// An application manifest, as alternative to writing the XML for [`set_manifest()`]
+///
+/// Unless [`identity()`] is used, the `assemblyIdentity` is the name of the package and the
+/// file version.
+///
+/// # Example
+///
+/// ```rust
+/// use winres::{DpiAwareness, ExecutionLevel, Manifest, WindowsVersion};
+///
+/// let mut res = winres::WindowsResource::new();
+/// res.set_application_manifest(Manifest::new()
+///     .execution_level(ExecutionLevel::RequireAdministrator)
+///     .dpi_awareness(DpiAwareness::PerMonitorV2)
+///     .long_path_aware(true)
+///     .utf8_code_page(true)
+///     .supported_os(WindowsVersion::Windows7)
+///     .common_controls_v6(true));
+/// # res.write_res_file(std::env::temp_dir().join("manifest.res")).unwrap();
+/// ```
+///
+/// [`set_manifest()`]: struct.WindowsResource.html#method.set_manifest
+/// [`identity()`]: #method.identity
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    identity: Option<(String, u64)>,
+    execution_level: Option<ExecutionLevel>,
+    ui_access: bool,
+    dpi_awareness: Option<DpiAwareness>,
+    long_path_aware: bool,
+    utf8_code_page: bool,
+    supported_os: Option<WindowsVersion>,
+    common_controls_v6: bool,
+    segment_heap: bool,
+}
+
+impl Manifest {
+    /// Create a manifest without any settings
+    pub fn new() -> Manifest {
+        Manifest::default()
+    }
+
+    /// Set the name and version of the `assemblyIdentity`
+    ///
+    /// The version has the same format as the one of [`set_version_info()`].
+    ///
+    /// [`set_version_info()`]: struct.WindowsResource.html#method.set_version_info
+    pub fn identity(&mut self, name: &str, version: u64) -> &mut Self {
+        self.identity = Some((name.to_string(), version));
+        self
+    }
+
+    /// Set the `requestedExecutionLevel`
+    pub fn execution_level(&mut self, level: ExecutionLevel) -> &mut Self {
+        self.execution_level = Some(level);
+        self
+    }
+
+    /// Allow the application to drive the UI of elevated windows, e.g., for accessibility
+    ///
+    /// Such an application has to be signed and installed in a secure location.
+    pub fn ui_access(&mut self, ui_access: bool) -> &mut Self {
+        self.ui_access = ui_access;
+        self
+    }
+
+    /// Set `dpiAware` and `dpiAwareness`
+    pub fn dpi_awareness(&mut self, awareness: DpiAwareness) -> &mut Self {
+        self.dpi_awareness = Some(awareness);
+        self
+    }
+
+    /// Allow paths longer than `MAX_PATH`, if enabled in the registry, from Windows 10 1607 on
+    pub fn long_path_aware(&mut self, long_path_aware: bool) -> &mut Self {
+        self.long_path_aware = long_path_aware;
+        self
+    }
+
+    /// Use UTF-8 as the code page of the `-A` functions, from Windows 10 1903 on
+    pub fn utf8_code_page(&mut self, utf8: bool) -> &mut Self {
+        self.utf8_code_page = utf8;
+        self
+    }
+
+    /// List `version` and all later versions of Windows as supported
+    pub fn supported_os(&mut self, version: WindowsVersion) -> &mut Self {
+        self.supported_os = Some(version);
+        self
+    }
+
+    /// Depend on version 6 of the Common Controls, for visual styles
+    pub fn common_controls_v6(&mut self, common_controls_v6: bool) -> &mut Self {
+        self.common_controls_v6 = common_controls_v6;
+        self
+    }
+
+    /// Use the segment heap as `heapType`, from Windows 10 2004 on
+    pub fn segment_heap(&mut self, segment_heap: bool) -> &mut Self {
+        self.segment_heap = segment_heap;
+        self
+    }
+
+    /// Render the XML, with `identity` unless one is set
+    pub(crate) fn to_xml(&self, identity: Option<(&str, u64)>) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+        xml.push_str(&format!("<assembly xmlns=\"{}\" manifestVersion=\"1.0\">\n", ASM_V1));
+        if let Some((name, version)) = self.identity.as_ref().map(|i| (i.0.as_str(), i.1)).or(identity) {
+            xml.push_str(&format!("  <assemblyIdentity type=\"win32\" name=\"{}\" version=\"{}.{}.{}.{}\" processorArchitecture=\"*\"/>\n",
+                                  xml_escape(name),
+                                  (version >> 48) as u16,
+                                  (version >> 32) as u16,
+                                  (version >> 16) as u16,
+                                  version as u16));
+        }
+        if self.common_controls_v6 {
+            xml.push_str("  <dependency>\n    <dependentAssembly>\n");
+            xml.push_str("      <assemblyIdentity type=\"win32\" name=\"Microsoft.Windows.Common-Controls\" version=\"6.0.0.0\" \
+                          processorArchitecture=\"*\" publicKeyToken=\"6595b64144ccf1df\" language=\"*\"/>\n");
+            xml.push_str("    </dependentAssembly>\n  </dependency>\n");
+        }
+        if self.execution_level.is_some() || self.ui_access {
+            let level = self.execution_level.unwrap_or(ExecutionLevel::AsInvoker);
+            xml.push_str(&format!("  <trustInfo xmlns=\"{}\">\n    <security>\n      <requestedPrivileges>\n", ASM_V3));
+            xml.push_str(&format!("        <requestedExecutionLevel level=\"{}\" uiAccess=\"{}\"/>\n", level.xml_name(), self.ui_access));
+            xml.push_str("      </requestedPrivileges>\n    </security>\n  </trustInfo>\n");
+        }
+        if let Some(oldest) = self.supported_os {
+            xml.push_str(&format!("  <compatibility xmlns=\"{}\">\n    <application>\n", COMPATIBILITY_V1));
+            for &(_, id) in WINDOWS_VERSIONS.iter().filter(|v| v.0 >= oldest) {
+                xml.push_str(&format!("      <supportedOS Id=\"{}\"/>\n", id));
+            }
+            xml.push_str("    </application>\n  </compatibility>\n");
+        }
+        let mut settings = Vec::new();
+        if let Some(awareness) = self.dpi_awareness {
+            let (aware, awareness) = awareness.xml_values();
+            settings.push(("dpiAware", "2005", aware));
+            settings.push(("dpiAwareness", "2016", awareness));
+        }
+        if self.long_path_aware {
+            settings.push(("longPathAware", "2016", "true"));
+        }
+        if self.utf8_code_page {
+            settings.push(("activeCodePage", "2019", "UTF-8"));
+        }
+        if self.segment_heap {
+            settings.push(("heapType", "2020", "SegmentHeap"));
+        }
+        if !settings.is_empty() {
+            xml.push_str(&format!("  <application xmlns=\"{}\">\n    <windowsSettings>\n", ASM_V3));
+            for (element, year, value) in settings {
+                xml.push_str(&format!("      <{0} xmlns=\"{1}{2}/WindowsSettings\">{3}</{0}>\n", element, SMI, year, value));
+            }
+            xml.push_str("    </windowsSettings>\n  </application>\n");
+        }
+        xml.push_str("</assembly>\n");
+        xml
+    }
+}
+
+/// Escape a string for an attribute value
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// An element a manifest may contain
+struct Element {
+    name: &'static str,
+    namespaces: &'static [&'static str],
+    /// the elements it may be a child of, none for the root
+    parents: &'static [&'static str],
+    /// the attributes without namespace it may have, `None` for any
+    attributes: Option<&'static [&'static str]>,
+}
+
+const ELEMENTS: [Element; 28] = [
+    Element { name: "assembly", namespaces: &[ASM_V1], parents: &[], attributes: Some(&["manifestVersion"]) },
+    Element {
+        name: "assemblyIdentity",
+        namespaces: &[ASM_V1],
+        parents: &["assembly", "dependentAssembly"],
+        attributes: Some(&["type", "name", "version", "processorArchitecture", "publicKeyToken", "language"]),
+    },
+    Element { name: "noInherit", namespaces: &[ASM_V1], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "noInheritable", namespaces: &[ASM_V1], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "description", namespaces: &[ASM_V1], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "dependency", namespaces: &[ASM_V1], parents: &["assembly"], attributes: Some(&["optional"]) },
+    Element {
+        name: "dependentAssembly",
+        namespaces: &[ASM_V1],
+        parents: &["dependency"],
+        attributes: Some(&["dependencyType", "allowDelayedBinding"]),
+    },
+    // registration free COM, the attributes depend on the kind of server
+    Element { name: "file", namespaces: &[ASM_V1], parents: &["assembly"], attributes: Some(&["name", "hash", "hashalg", "size"]) },
+    Element { name: "comClass", namespaces: &[ASM_V1], parents: &["file"], attributes: None },
+    Element { name: "progid", namespaces: &[ASM_V1], parents: &["comClass"], attributes: Some(&[]) },
+    Element { name: "typelib", namespaces: &[ASM_V1], parents: &["file"], attributes: None },
+    Element { name: "comInterfaceProxyStub", namespaces: &[ASM_V1], parents: &["file"], attributes: None },
+    Element { name: "comInterfaceExternalProxyStub", namespaces: &[ASM_V1], parents: &["assembly"], attributes: None },
+    Element { name: "windowClass", namespaces: &[ASM_V1], parents: &["file"], attributes: None },
+    Element { name: "clrClass", namespaces: &[ASM_V1], parents: &["assembly"], attributes: None },
+    Element { name: "clrSurrogate", namespaces: &[ASM_V1], parents: &["assembly"], attributes: None },
+    Element { name: "activatableClass", namespaces: &[WINRT_V1], parents: &["file"], attributes: Some(&["name", "threadingModel"]) },
+    // older manifests have the trust info in asm.v2
+    Element { name: "trustInfo", namespaces: &[ASM_V3, ASM_V2], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "security", namespaces: &[ASM_V3, ASM_V2], parents: &["trustInfo"], attributes: Some(&[]) },
+    Element { name: "requestedPrivileges", namespaces: &[ASM_V3, ASM_V2], parents: &["security"], attributes: Some(&[]) },
+    Element {
+        name: "requestedExecutionLevel",
+        namespaces: &[ASM_V3, ASM_V2],
+        parents: &["requestedPrivileges"],
+        attributes: Some(&["level", "uiAccess"]),
+    },
+    Element { name: "application", namespaces: &[ASM_V3], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "windowsSettings", namespaces: &[ASM_V3], parents: &["application"], attributes: Some(&[]) },
+    Element { name: "compatibility", namespaces: &[COMPATIBILITY_V1], parents: &["assembly"], attributes: Some(&[]) },
+    Element { name: "application", namespaces: &[COMPATIBILITY_V1], parents: &["compatibility"], attributes: Some(&[]) },
+    Element { name: "supportedOS", namespaces: &[COMPATIBILITY_V1], parents: &["application"], attributes: Some(&["Id"]) },
+    Element { name: "maxversiontested", namespaces: &[COMPATIBILITY_V1], parents: &["application"], attributes: Some(&["Id"]) },
+    Element {
+        name: "msix",
+        namespaces: &[MSIX_V1],
+        parents: &["assembly"],
+        attributes: Some(&["publisher", "packageName", "applicationId"]),
+    },
+];
+
+/// The elements of `windowsSettings` and the year in their namespace
+const SETTINGS: [(&str, &str); 13] = [("autoElevate", "2005"),
+                                      ("disableTheming", "2005"),
+                                      ("dpiAware", "2005"),
+                                      ("highResolutionScrollingAware", "2005"),
+                                      ("ultraHighResolutionScrollingAware", "2005"),
+                                      ("disableWindowFiltering", "2011"),
+                                      ("printerDriverIsolation", "2011"),
+                                      ("dpiAwareness", "2016"),
+                                      ("longPathAware", "2016"),
+                                      ("gdiScaling", "2017"),
+                                      ("activeCodePage", "2019"),
+                                      ("heapType", "2020"),
+                                      ("supportedArchitectures", "2024")];
+
+/// Check that the manifest is well-formed and contains only known elements and attributes
+///
+/// The error has the line and column of the problem.
+pub(crate) fn check_xml(xml: &str) -> ::std::result::Result<(), (TextPos, String)> {
+    let doc = Document::parse(xml).map_err(|e| {
+        // the position is also at the end of the message
+        let message = e.to_string();
+        let suffix = format!(" at {}", e.pos());
+        (e.pos(), message.trim_end_matches(suffix.as_str()).to_string())
+    })?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "assembly" || root.tag_name().namespace() != Some(ASM_V1) {
+        return Err(error_at(root, format!("the root element has to be <assembly xmlns=\"{}\">", ASM_V1)));
+    }
+    check_element(root)
+}
+
+/// Decode a manifest file, UTF-8 or, with a byte order mark, UTF-16
+///
+/// A UTF-8 byte order mark is removed, the XML parser does not expect one.
+pub(crate) fn decode(buf: &[u8]) -> Option<String> {
+    let utf16 = |from_bytes: fn([u8; 2]) -> u16| {
+        let chunks = buf[2..].chunks_exact(2);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        let units: Vec<u16> = chunks.map(|c| from_bytes([c[0], c[1]])).collect();
+        String::from_utf16(&units).ok()
+    };
+    match buf {
+        [0xFF, 0xFE, ..] => utf16(u16::from_le_bytes),
+        [0xFE, 0xFF, ..] => utf16(u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec()).ok(),
+        _ => String::from_utf8(buf.to_vec()).ok(),
+    }
+}
+
+fn check_element(node: Node) -> ::std::result::Result<(), (TextPos, String)> {
+    let name = node.tag_name().name();
+    let namespace = node.tag_name().namespace().unwrap_or("");
+    let parent = node.parent_element().map(|p| p.tag_name().name());
+    if parent == Some("windowsSettings") && namespace.starts_with(SMI) {
+        return check_setting(node);
+    }
+    let element = match ELEMENTS.iter().find(|e| e.name == name && e.namespaces.contains(&namespace)) {
+        Some(element) => element,
+        None => {
+            // a known element in the wrong namespace, or in the wrong place
+            let message = match ELEMENTS.iter().filter(|e| e.name == name).find(|e| parent.iter().all(|p| e.parents.contains(p))) {
+                Some(e) => format!("<{}> has to be in the namespace {}", name, e.namespaces[0]),
+                None if ELEMENTS.iter().any(|e| e.name == name) => format!("<{}> is not allowed in <{}>", name, parent.unwrap_or("")),
+                None if ELEMENTS.iter().any(|e| e.namespaces.contains(&namespace)) => format!("the element <{}> is unknown", name),
+                None if namespace.is_empty() => format!("<{}> has no namespace", name),
+                None => format!("the namespace {} of <{}> is unknown", namespace, name),
+            };
+            return Err(error_at(node, message));
+        }
+    };
+    if let Some(parent) = parent {
+        if !element.parents.contains(&parent) {
+            return Err(error_at(node, format!("<{}> is not allowed in <{}>", name, parent)));
+        }
+    }
+    for attribute in node.attributes().filter(|a| a.namespace().is_none()) {
+        if let Some(known) = element.attributes {
+            if !known.contains(&attribute.name()) {
+                let pos = node.document().text_pos_at(attribute.range().start);
+                return Err((pos, format!("the attribute {} of <{}> is unknown", attribute.name(), name)));
+            }
+        }
+    }
+    check_attributes(node)?;
+    for child in node.children().filter(|c| c.is_element()) {
+        check_element(child)?;
+    }
+    Ok(())
+}
+
+/// Check the values of the attributes that Windows reads
+fn check_attributes(node: Node) -> ::std::result::Result<(), (TextPos, String)> {
+    let name = node.tag_name().name();
+    let required = match name {
+        "assembly" => Some("manifestVersion"),
+        "assemblyIdentity" => Some("name"),
+        "requestedExecutionLevel" => Some("level"),
+        "supportedOS" | "maxversiontested" => Some("Id"),
+        _ => None,
+    };
+    if let Some(attribute) = required {
+        if node.attribute(attribute).is_none() {
+            return Err(error_at(node, format!("<{}> needs the attribute {}", name, attribute)));
+        }
+    }
+    for attribute in node.attributes().filter(|a| a.namespace().is_none()) {
+        let value = attribute.value();
+        let valid = match (name, attribute.name()) {
+            ("assembly", "manifestVersion") => value == "1.0",
+            ("assemblyIdentity", "version") | ("maxversiontested", "Id") => is_version(value),
+            ("requestedExecutionLevel", "level") => ["asInvoker", "highestAvailable", "requireAdministrator"].contains(&value),
+            ("requestedExecutionLevel", "uiAccess") => value == "true" || value == "false",
+            ("supportedOS", "Id") => WINDOWS_VERSIONS.iter().any(|v| v.1.eq_ignore_ascii_case(value)),
+            _ => true,
+        };
+        if !valid {
+            let pos = node.document().text_pos_at(attribute.range_value().start);
+            return Err((pos, format!("\"{}\" is not a valid value of the attribute {} of <{}>", value, attribute.name(), name)));
+        }
+    }
+    Ok(())
+}
+
+/// Check an element of `windowsSettings`, its namespace and its value
+fn check_setting(node: Node) -> ::std::result::Result<(), (TextPos, String)> {
+    let name = node.tag_name().name();
+    let year = match SETTINGS.iter().find(|s| s.0 == name) {
+        Some(setting) => setting.1,
+        None => return Err(error_at(node, format!("the setting <{}> is unknown", name))),
+    };
+    let namespace = format!("{}{}/WindowsSettings", SMI, year);
+    if node.tag_name().namespace() != Some(namespace.as_str()) {
+        return Err(error_at(node, format!("<{}> has to be in the namespace {}", name, namespace)));
+    }
+    if let Some(child) = node.children().find(|c| c.is_element()) {
+        return Err(error_at(child, format!("<{}> can not contain elements", name)));
+    }
+    let value = node.text().unwrap_or("").trim();
+    let is = |values: &[&str], v: &str| values.iter().any(|x| x.eq_ignore_ascii_case(v.trim()));
+    let valid = match name {
+        "dpiAware" => is(&["true", "false", "true/pm", "per monitor"], value),
+        "dpiAwareness" => value.split(',').all(|v| is(&["unaware", "system", "permonitor", "permonitorv2"], v)),
+        "activeCodePage" => !value.is_empty(),
+        "heapType" => is(&["SegmentHeap"], value),
+        "supportedArchitectures" => value.split_whitespace().all(|v| is(&["amd64", "arm64"], v)) && !value.is_empty(),
+        _ => is(&["true", "false"], value),
+    };
+    if !valid {
+        return Err(error_at(node, format!("\"{}\" is not a valid value of <{}>", value, name)));
+    }
+    Ok(())
+}
+
+/// A version of four numbers, e.g., `1.0.0.0`
+fn is_version(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('.').collect();
+    parts.len() == 4 && parts.iter().all(|p| p.parse::<u16>().is_ok())
+}
+
+fn error_at(node: Node, message: String) -> (TextPos, String) {
+    (node.document().text_pos_at(node.range().start), message)
+}
@@ -0,0 +1,355 @@
+//! Menu templates
+//!
+//! A menu is written as `MENU` statement to the resource script, or as
+//! `MENUITEMTEMPLATEHEADER` followed by the `MENUITEMTEMPLATE`s, where the items
+//! of a popup follow the popup item and the last item of every level is marked.
+//! Default items and radio checks need the extended format, the `MENUEX` statement and
+//! its `MENUEX_TEMPLATE_HEADER` and `MENUEX_TEMPLATE_ITEM`s, which llvm-rc only
+//! understands from version 18 on.
+
+use std::io;
+use std::io::prelude::*;
+
+use res;
+use rc_string;
+
+const MFT_SEPARATOR: u32 = 0x0800;
+const MFT_RADIOCHECK: u32 = 0x0200;
+const MFT_RIGHTJUSTIFY: u32 = 0x4000;
+const MFS_GRAYED: u32 = 0x0003;
+const MFS_CHECKED: u32 = 0x0008;
+const MFS_DEFAULT: u32 = 0x1000;
+// the flags of the standard format
+const MF_GRAYED: u16 = 0x0001;
+const MF_CHECKED: u16 = 0x0008;
+const MF_POPUP: u16 = 0x0010;
+const MF_END: u16 = 0x0080;
+const MF_HELP: u16 = 0x4000;
+
+/// A menu bar or popup menu, built like the `MENUEX` statement of a resource script
+///
+/// # Example
+///
+/// ```rust
+/// use winres::{Menu, MenuItem};
+///
+/// let mut file = Menu::new();
+/// file.add_item(MenuItem::new("&Open...\tCtrl+O", 101))
+///     .add_item(MenuItem::new("&Autosave", 102).checked())
+///     .add_item(MenuItem::separator())
+///     .add_item(MenuItem::new("E&xit", 103));
+///
+/// let mut res = winres::WindowsResource::new();
+/// res.add_menu(1, Menu::new()
+///     .add_item(MenuItem::popup("&File", &file))
+///     .add_item(MenuItem::new("&Help", 104).right_justify()));
+/// # res.write_res_file(std::env::temp_dir().join("menu.res")).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Menu {
+    items: Vec<MenuItem>,
+}
+
+impl Menu {
+    /// Create an empty menu
+    pub fn new() -> Menu {
+        Menu { items: Vec::new() }
+    }
+
+    /// Add an item at the end
+    pub fn add_item(&mut self, item: MenuItem) -> &mut Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Check that neither the menu nor any popup is empty, the binary format can not express that
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.items.is_empty() {
+            return Err("the menu is empty".to_string());
+        }
+        self.check_popups()
+    }
+
+    fn check_popups(&self) -> Result<(), String> {
+        for item in &self.items {
+            if let Some(ref popup) = item.popup {
+                if popup.items.is_empty() {
+                    return Err(format!("the popup \"{}\" is empty", item.text));
+                }
+                popup.check_popups()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the menu or one of its popups needs the extended format
+    fn is_extended(&self) -> bool {
+        self.items.iter().any(|item| {
+            item.item_type & MFT_RADIOCHECK != 0 || item.state & MFS_DEFAULT != 0 ||
+            matches!(item.popup, Some(ref p) if p.is_extended())
+        })
+    }
+
+    /// Write the `MENU` or `MENUEX` statement
+    ///
+    /// Not every resource compiler understands the `LANGUAGE` statement inside `MENUEX`,
+    /// the caller has to set the language before.
+    pub(crate) fn write_rc<W: Write>(&self, w: &mut W, id: &res::ResourceId) -> io::Result<()> {
+        if self.is_extended() {
+            writeln!(w, "{} MENUEX", id)?;
+            self.write_items_ex(w)
+        } else {
+            writeln!(w, "{} MENU", id)?;
+            self.write_items(w)
+        }
+    }
+
+    fn write_items<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "{{")?;
+        for item in &self.items {
+            if item.item_type & MFT_SEPARATOR != 0 {
+                writeln!(w, "MENUITEM SEPARATOR")?;
+                continue;
+            }
+            let mut options = String::new();
+            for &(flag, name) in &[(MF_CHECKED, "CHECKED"), (MF_GRAYED, "GRAYED"), (MF_HELP, "HELP")] {
+                if item.flags() & flag != 0 {
+                    options.push_str(", ");
+                    options.push_str(name);
+                }
+            }
+            match item.popup {
+                Some(ref popup) => {
+                    writeln!(w, "POPUP \"{}\"{}", rc_string(&item.text), options)?;
+                    popup.write_items(w)?;
+                }
+                None => writeln!(w, "MENUITEM \"{}\", {}{}", rc_string(&item.text), item.id, options)?,
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    fn write_items_ex<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "{{")?;
+        for item in &self.items {
+            match item.popup {
+                Some(ref popup) => {
+                    writeln!(w, "POPUP \"{}\", {}, {:#x}, {:#x}", rc_string(&item.text), item.id, item.item_type, item.state)?;
+                    popup.write_items_ex(w)?;
+                }
+                None => {
+                    writeln!(w, "MENUITEM \"{}\", {}, {:#x}, {:#x}", rc_string(&item.text), item.id, item.item_type, item.state)?
+                }
+            }
+        }
+        writeln!(w, "}}")
+    }
+
+    /// Serialize the menu template, in the same format as `write_rc()`
+    pub(crate) fn template(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        if self.is_extended() {
+            res::push_u16(&mut buf, 1); // wVersion
+            res::push_u16(&mut buf, 4); // wOffset, the items follow the help id
+            res::push_u32(&mut buf, 0); // dwHelpId
+            self.push_items_ex(&mut buf);
+        } else {
+            res::push_u16(&mut buf, 0); // versionNumber
+            res::push_u16(&mut buf, 0); // offset
+            self.push_items(&mut buf);
+        }
+        buf
+    }
+
+    fn push_items(&self, buf: &mut Vec<u8>) {
+        for (i, item) in self.items.iter().enumerate() {
+            let mut flags = item.flags();
+            if i + 1 == self.items.len() {
+                flags |= MF_END;
+            }
+            res::push_u16(buf, flags);
+            // popups have no id, separators are empty items
+            if item.popup.is_none() {
+                res::push_u16(buf, item.id);
+            }
+            res::push_wstr(buf, &item.text);
+            if let Some(ref popup) = item.popup {
+                popup.push_items(buf);
+            }
+        }
+    }
+
+    fn push_items_ex(&self, buf: &mut Vec<u8>) {
+        for (i, item) in self.items.iter().enumerate() {
+            res::push_u32(buf, item.item_type);
+            res::push_u32(buf, item.state);
+            res::push_u32(buf, item.id as u32);
+            let mut flags = 0;
+            if item.popup.is_some() {
+                flags |= 0x01;
+            }
+            if i + 1 == self.items.len() {
+                flags |= 0x80;
+            }
+            res::push_u16(buf, flags);
+            res::push_wstr(buf, &item.text);
+            res::align(buf);
+            if let Some(ref popup) = item.popup {
+                res::push_u32(buf, 0); // dwHelpId
+                popup.push_items_ex(buf);
+            }
+        }
+    }
+}
+
+/// An item of a [`Menu`]: a command, a separator or a popup menu
+///
+/// The text may contain a `&` before the access key, and a tab followed by the
+/// shortcut, e.g., `"&Save\tCtrl+S"`. The shortcut itself is defined by [`Accelerators`].
+///
+/// [`Menu`]: struct.Menu.html
+/// [`Accelerators`]: struct.Accelerators.html
+#[derive(Clone, Debug)]
+pub struct MenuItem {
+    text: String,
+    id: u16,
+    item_type: u32,
+    state: u32,
+    popup: Option<Menu>,
+}
+
+impl MenuItem {
+    /// A command, the id is sent with the `WM_COMMAND` message
+    pub fn new(text: &str, id: u16) -> MenuItem {
+        MenuItem {
+            text: text.to_string(),
+            id,
+            item_type: 0,
+            state: 0,
+            popup: None,
+        }
+    }
+
+    /// A line between two groups of items
+    pub fn separator() -> MenuItem {
+        MenuItem {
+            item_type: MFT_SEPARATOR,
+            ..MenuItem::new("", 0)
+        }
+    }
+
+    /// An item that opens the menu `popup`
+    pub fn popup(text: &str, popup: &Menu) -> MenuItem {
+        MenuItem {
+            popup: Some(popup.clone()),
+            ..MenuItem::new(text, 0)
+        }
+    }
+
+    /// Show the item with a check mark
+    pub fn checked(mut self) -> MenuItem {
+        self.state |= MFS_CHECKED;
+        self
+    }
+
+    /// Show the item disabled, it can not be selected
+    pub fn grayed(mut self) -> MenuItem {
+        self.state |= MFS_GRAYED;
+        self
+    }
+
+    /// Show the item in bold, as the default action of the menu
+    ///
+    /// The menu is then written in the extended format, `MENUEX`, that needs llvm-rc 18
+    /// or later with [`ResourceCompiler::Llvm`].
+    ///
+    /// [`ResourceCompiler::Llvm`]: enum.ResourceCompiler.html#variant.Llvm
+    pub fn default_item(mut self) -> MenuItem {
+        self.state |= MFS_DEFAULT;
+        self
+    }
+
+    /// Use a dot instead of a check mark for [`checked()`]
+    ///
+    /// The menu is then written in the extended format, see [`default_item()`].
+    ///
+    /// [`checked()`]: #method.checked
+    /// [`default_item()`]: #method.default_item
+    pub fn radio_check(mut self) -> MenuItem {
+        self.item_type |= MFT_RADIOCHECK;
+        self
+    }
+
+    /// Move this and the following items of a menu bar to the right
+    pub fn right_justify(mut self) -> MenuItem {
+        self.item_type |= MFT_RIGHTJUSTIFY;
+        self
+    }
+
+    /// The flags of the standard format, without `MF_END`
+    fn flags(&self) -> u16 {
+        let mut flags = 0;
+        if self.popup.is_some() {
+            flags |= MF_POPUP;
+        }
+        if self.state & MFS_CHECKED != 0 {
+            flags |= MF_CHECKED;
+        }
+        if self.state & MFS_GRAYED != 0 {
+            flags |= MF_GRAYED;
+        }
+        if self.item_type & MFT_RIGHTJUSTIFY != 0 {
+            flags |= MF_HELP;
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_template() {
+        // POPUP "&File" { MENUITEM "&Open...\tCtrl+O", 101, MENUITEM SEPARATOR,
+        // MENUITEM "E&xit", 102, GRAYED } MENUITEM "&Help", 103, CHECKED, HELP,
+        // as compiled by llvm-rc
+        const EXPECTED: [u8; 92] = [
+            0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x26, 0x00, 0x46, 0x00, 0x69, 0x00, 0x6c, 0x00, 0x65, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x65, 0x00, 0x26, 0x00, 0x4f, 0x00, 0x70, 0x00, 0x65, 0x00, 0x6e, 0x00,
+            0x2e, 0x00, 0x2e, 0x00, 0x2e, 0x00, 0x09, 0x00, 0x43, 0x00, 0x74, 0x00, 0x72, 0x00, 0x6c, 0x00,
+            0x2b, 0x00, 0x4f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x81, 0x00, 0x66, 0x00,
+            0x45, 0x00, 0x26, 0x00, 0x78, 0x00, 0x69, 0x00, 0x74, 0x00, 0x00, 0x00, 0x88, 0x40, 0x67, 0x00,
+            0x26, 0x00, 0x48, 0x00, 0x65, 0x00, 0x6c, 0x00, 0x70, 0x00, 0x00, 0x00,
+        ];
+        let mut file = Menu::new();
+        file.add_item(MenuItem::new("&Open...\tCtrl+O", 101))
+            .add_item(MenuItem::separator())
+            .add_item(MenuItem::new("E&xit", 102).grayed());
+        let mut menu = Menu::new();
+        menu.add_item(MenuItem::popup("&File", &file))
+            .add_item(MenuItem::new("&Help", 103).right_justify().checked());
+        assert!(!menu.is_extended());
+        assert_eq!(&menu.template()[..], &EXPECTED[..]);
+    }
+
+    #[test]
+    fn menuex_template() {
+        const EXPECTED: [u8; 52] = [
+            // MENUEX_TEMPLATE_HEADER: wVersion, wOffset, dwHelpId
+            0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // POPUP "&F": dwType, dwState, uId, wFlags (popup and last item), "&F", dwHelpId
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x81, 0x00,
+            0x26, 0x00, 0x46, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // MENUITEM "A", 101, MFT_RADIOCHECK, MFS_DEFAULT, the last item, aligned to 32 bits
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x65, 0x00, 0x00, 0x00, 0x80, 0x00,
+            0x41, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut popup = Menu::new();
+        popup.add_item(MenuItem::new("A", 101).radio_check().default_item());
+        let mut menu = Menu::new();
+        menu.add_item(MenuItem::popup("&F", &popup));
+        assert!(menu.is_extended());
+        assert_eq!(&menu.template()[..], &EXPECTED[..]);
+    }
+}
@@ -0,0 +1,413 @@
+//! Message tables, e.g., for the Event Log
+//!
+//! A message table is what `mc.exe` generates from a message text file (`.mc`): one
+//! `MESSAGE_RESOURCE_DATA` resource per language, holding blocks of consecutive message ids
+//! and the texts, which `FormatMessage()` formats. Besides the resource, this module writes
+//! the message ids as Rust constants.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use error::{Error, Result};
+use res;
+
+/// The severity in the two highest bits of a message id
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum MessageSeverity {
+    Success,
+    Informational,
+    Warning,
+    Error,
+}
+
+impl MessageSeverity {
+    pub fn value(self) -> u32 {
+        match self {
+            MessageSeverity::Success => 0,
+            MessageSeverity::Informational => 1,
+            MessageSeverity::Warning => 2,
+            MessageSeverity::Error => 3,
+        }
+    }
+}
+
+/// The messages of an application or service, built in code or read from a `.mc` file
+///
+/// Every message has a symbolic name, used for the generated constant, and a 32-bit id,
+/// that is composed of severity, facility and code, see [`message_id()`]. Event Viewer
+/// shows the code as event id. Inserts like `%1` are replaced by `FormatMessage()`.
+///
+/// # Example
+///
+/// ```rust
+/// use winres::{MessageSeverity, MessageTable};
+///
+/// let mut messages = MessageTable::new();
+/// messages.add("SERVICE_STARTED", 0x1000, "The service was started.\r\n")
+///         .add("SERVICE_FAILED",
+///              MessageTable::message_id(MessageSeverity::Error, 0, 0x1001),
+///              "The service failed: %1\r\n")
+///         .translate(0x0407, 0x1000, "Der Dienst wurde gestartet.\r\n");
+///
+/// let mut res = winres::WindowsResource::new();
+/// res.set_message_table(&messages);
+/// # res.write_res_file(std::env::temp_dir().join("messages.res")).unwrap();
+/// # messages.write_constants(std::env::temp_dir().join("messages.rs")).unwrap();
+/// ```
+///
+/// The constants are written to `messages.rs` in the output directory by `compile()`, or
+/// anywhere else with [`write_constants()`]:
+///
+/// ```rust,ignore
+/// include!(concat!(env!("OUT_DIR"), "/messages.rs"));
+/// ```
+///
+/// [`message_id()`]: #method.message_id
+/// [`write_constants()`]: #method.write_constants
+#[derive(Clone, Debug, Default)]
+pub struct MessageTable {
+    messages: BTreeMap<u32, Message>,
+    /// the `.mc` file the messages were read from
+    source: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Message {
+    name: String,
+    /// the texts in the order they were added, `None` is the language of the resource
+    texts: Vec<(Option<u16>, String)>,
+}
+
+impl MessageTable {
+    /// Create an empty message table
+    pub fn new() -> MessageTable {
+        MessageTable {
+            messages: BTreeMap::new(),
+            source: None,
+        }
+    }
+
+    /// Read a message text file, as written for `mc.exe`
+    ///
+    /// The header may define `SeverityNames`, `FacilityNames` and `LanguageNames`, every
+    /// message has a `MessageId`, `Severity`, `Facility`, `SymbolicName` and its texts, each
+    /// after a `Language` line and terminated by a line with a single `.`. As with `mc.exe`,
+    /// every line of a text ends with `\r\n`. Without `LanguageNames`, `English` is 0x409.
+    pub fn from_mc_file<P: AsRef<Path>>(path: P) -> Result<MessageTable> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut table = parse_mc(&text).map_err(|(line, message)| {
+            Error::InvalidMessageFile {
+                path: path.to_path_buf(),
+                line,
+                message,
+            }
+        })?;
+        table.source = Some(path.to_path_buf());
+        Ok(table)
+    }
+
+    /// Compose a message id, the facility has 12 bits
+    pub fn message_id(severity: MessageSeverity, facility: u16, code: u16) -> u32 {
+        severity.value() << 30 | (facility as u32 & 0xFFF) << 16 | code as u32
+    }
+
+    /// Add a message in the language of the resource
+    pub fn add(&mut self, name: &str, id: u32, text: &str) -> &mut Self {
+        let message = self.messages.entry(id).or_default();
+        message.name = name.to_string();
+        message.set_text(None, text);
+        self
+    }
+
+    /// Add the text of a message for another language
+    pub fn translate(&mut self, language: u16, id: u32, text: &str) -> &mut Self {
+        self.messages.entry(id).or_default().set_text(Some(language), text);
+        self
+    }
+
+    /// Write the message ids as Rust constants, documented with the first line of the first text
+    pub fn write_constants<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.check().map_err(Error::InvalidResource)?;
+        let mut out = String::from("// Message ids, generated by winres\n");
+        for (id, message) in self.messages.iter().filter(|m| !m.1.name.is_empty()) {
+            out.push('\n');
+            if let Some((_, text)) = message.texts.first() {
+                let line = text.lines().next().unwrap_or("").trim();
+                if !line.is_empty() {
+                    out.push_str(&format!("/// {}\n", line));
+                }
+            }
+            out.push_str(&format!("#[allow(dead_code)]\npub const {}: u32 = {:#010x};\n", message.name, id));
+        }
+        fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// The file the messages were read from
+    pub(crate) fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Check the names, and that every message has a text
+    pub(crate) fn check(&self) -> ::std::result::Result<(), String> {
+        let mut names = BTreeMap::new();
+        for (&id, message) in &self.messages {
+            if message.texts.is_empty() {
+                return Err(format!("message {:#x} has no text", id));
+            }
+            if message.name.is_empty() {
+                continue;
+            }
+            if message.name.starts_with(|c: char| c.is_ascii_digit()) ||
+               !message.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("{:?} is not a valid name for message {:#x}", message.name, id));
+            }
+            if let Some(other) = names.insert(message.name.as_str(), id) {
+                return Err(format!("the messages {:#x} and {:#x} are both named {}", other, id, message.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `MESSAGE_RESOURCE_DATA` of every language
+    ///
+    /// Texts without language are stored for `language`.
+    pub(crate) fn resources(&self, language: u16) -> BTreeMap<u16, Vec<u8>> {
+        let mut languages: BTreeMap<u16, BTreeMap<u32, &str>> = BTreeMap::new();
+        // a translation into the language of the resource wins over the text without language
+        for (&id, message) in &self.messages {
+            for &(l, ref text) in &message.texts {
+                let texts = languages.entry(l.unwrap_or(language)).or_default();
+                if l.is_some() || !texts.contains_key(&id) {
+                    texts.insert(id, text);
+                }
+            }
+        }
+        languages.into_iter().map(|(l, texts)| (l, message_data(&texts))).collect()
+    }
+}
+
+impl Message {
+    fn set_text(&mut self, language: Option<u16>, text: &str) {
+        match self.texts.iter_mut().find(|t| t.0 == language) {
+            Some(t) => t.1 = text.to_string(),
+            None => self.texts.push((language, text.to_string())),
+        }
+    }
+}
+
+/// Serialize the blocks of consecutive ids and their entries
+fn message_data(texts: &BTreeMap<u32, &str>) -> Vec<u8> {
+    let mut blocks: Vec<(u32, u32)> = Vec::new();
+    for &id in texts.keys() {
+        match blocks.last_mut() {
+            Some(block) if block.1.checked_add(1) == Some(id) => block.1 = id,
+            _ => blocks.push((id, id)),
+        }
+    }
+    let mut entries = Vec::new();
+    let mut offsets = Vec::with_capacity(blocks.len());
+    let entries_start = 4 + 12 * blocks.len();
+    for &(low, high) in &blocks {
+        offsets.push(entries_start + entries.len());
+        for id in low..=high {
+            // MESSAGE_RESOURCE_ENTRY: length, MESSAGE_RESOURCE_UNICODE and the zero terminated text
+            let text = res::wstr(texts[&id]);
+            let length = 4 + text.len() + res::padding(text.len());
+            res::push_u16(&mut entries, length as u16);
+            res::push_u16(&mut entries, 1);
+            entries.extend_from_slice(&text);
+            res::align(&mut entries);
+        }
+    }
+    let mut buf = Vec::with_capacity(entries_start + entries.len());
+    res::push_u32(&mut buf, blocks.len() as u32);
+    for (&(low, high), &offset) in blocks.iter().zip(&offsets) {
+        res::push_u32(&mut buf, low);
+        res::push_u32(&mut buf, high);
+        res::push_u32(&mut buf, offset as u32);
+    }
+    buf.extend_from_slice(&entries);
+    buf
+}
+
+/// Parse a message text file, errors have the line number
+fn parse_mc(text: &str) -> ::std::result::Result<MessageTable, (usize, String)> {
+    let mut severities: BTreeMap<String, u32> =
+        [("success", 0), ("informational", 1), ("warning", 2), ("error", 3)].iter().map(|&(n, v)| (n.to_string(), v)).collect();
+    let mut facilities: BTreeMap<String, u32> =
+        [("system", 0x0FF), ("application", 0xFFF)].iter().map(|&(n, v)| (n.to_string(), v)).collect();
+    let mut languages: BTreeMap<String, u16> = BTreeMap::new();
+    languages.insert("english".to_string(), 0x409);
+
+    let mut table = MessageTable::new();
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l.trim_end_matches('\r')));
+    // severity and facility are kept from one message to the next, the id of a message is
+    // complete with its first text, until then `code` is the value of `MessageId`, if any
+    let (mut severity, mut facility) = (0, 0);
+    let mut last_codes: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut code: Option<(bool, u32)> = None;
+    let mut name = String::new();
+    let mut id: Option<u32> = None;
+    // the line of a `MessageId` that has no text yet
+    let mut pending: Option<usize> = None;
+
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim().to_lowercase(), line[i + 1..].trim()),
+            None => return Err((number, format!("expected a keyword, found \"{}\"", line))),
+        };
+        match key.as_str() {
+            "messageidtypedef" | "outputbase" => {}
+            "severitynames" | "facilitynames" | "languagenames" => {
+                // the list may continue on the following lines
+                let mut list = value.to_string();
+                while !list.contains(')') {
+                    match lines.next() {
+                        Some((_, l)) => {
+                            list.push(' ');
+                            list.push_str(l.trim());
+                        }
+                        None => return Err((number, format!("the list of {} is not closed", key))),
+                    }
+                }
+                let list = list.trim().trim_start_matches('(');
+                let list = &list[..list.find(')').unwrap()];
+                for item in list.split_whitespace() {
+                    let (n, v) = match item.find('=') {
+                        Some(i) => (&item[..i], &item[i + 1..]),
+                        None => return Err((number, format!("expected name=value, found \"{}\"", item))),
+                    };
+                    // the symbol or file name after the colon is not needed
+                    let v = v.split(':').next().unwrap();
+                    let v = parse_number(v).ok_or_else(|| (number, format!("\"{}\" is not a number", v)))?;
+                    let n = n.to_lowercase();
+                    match key.as_str() {
+                        "severitynames" if v <= 3 => severities.insert(n, v).map(|_| ()),
+                        "facilitynames" if v <= 0xFFF => facilities.insert(n, v).map(|_| ()),
+                        "languagenames" if v <= 0xFFFF => languages.insert(n, v as u16).map(|_| ()),
+                        _ => return Err((number, format!("the value of {} is too large", item))),
+                    };
+                }
+            }
+            "messageid" => {
+                if let Some(line) = pending {
+                    return Err((line, "the message has no text".to_string()));
+                }
+                code = match value {
+                    "" => None,
+                    v if v.starts_with('+') => Some((true, parse_number(&v[1..]).ok_or_else(|| (number, format!("\"{}\" is not a message id", v)))?)),
+                    v => Some((false, parse_number(v).ok_or_else(|| (number, format!("\"{}\" is not a message id", v)))?)),
+                };
+                pending = Some(number);
+                name.clear();
+                id = None;
+            }
+            "severity" => {
+                severity = *severities.get(&value.to_lowercase())
+                    .ok_or_else(|| (number, format!("the severity {} is unknown", value)))?;
+            }
+            "facility" => {
+                facility = *facilities.get(&value.to_lowercase())
+                    .ok_or_else(|| (number, format!("the facility {} is unknown", value)))?;
+            }
+            "symbolicname" => name = value.to_string(),
+            "language" => {
+                let language = *languages.get(&value.to_lowercase())
+                    .ok_or_else(|| (number, format!("the language {} is unknown", value)))?;
+                let message_id = match id {
+                    Some(id) => id,
+                    None => {
+                        // the first text of a message, its id is complete now
+                        let c = match code.take() {
+                            Some((false, c)) => c,
+                            Some((true, n)) => last_codes.get(&facility).map_or(n, |c| c + n),
+                            None => last_codes.get(&facility).map_or(0, |c| c + 1),
+                        };
+                        if c > 0xFFFF {
+                            return Err((number, format!("the message id {:#x} is larger than 0xffff", c)));
+                        }
+                        last_codes.insert(facility, c);
+                        let message_id = severity << 30 | facility << 16 | c;
+                        if table.messages.contains_key(&message_id) {
+                            return Err((number, format!("the message id {:#x} is used twice", message_id)));
+                        }
+                        pending = None;
+                        id = Some(message_id);
+                        message_id
+                    }
+                };
+                let mut text = String::new();
+                loop {
+                    match lines.next() {
+                        Some((_, ".")) => break,
+                        Some((_, l)) => {
+                            text.push_str(l);
+                            text.push_str("\r\n");
+                        }
+                        None => return Err((number, "the text is not terminated by a line with a single \".\"".to_string())),
+                    }
+                }
+                table.translate(language, message_id, &text);
+                table.messages.get_mut(&message_id).unwrap().name = name.clone();
+            }
+            _ => return Err((number, format!("the keyword {} is unknown", line[..line.find('=').unwrap()].trim()))),
+        }
+    }
+    match pending {
+        Some(line) => Err((line, "the message has no text".to_string())),
+        None => Ok(table),
+    }
+}
+
+/// A decimal or hexadecimal number
+fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if s.starts_with("0x") || s.starts_with("0X") {
+        u32::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_resources() {
+        const ENGLISH: [u8; 64] = [
+            // NumberOfBlocks, then LowId, HighId and OffsetToEntries of the blocks 1..=2 and 0xC0000005
+            0x02, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x00, 0xc0, 0x05, 0x00, 0x00, 0xc0, 0x38, 0x00, 0x00, 0x00,
+            // Length, Flags (MESSAGE_RESOURCE_UNICODE) and "A\r\n"
+            0x0c, 0x00, 0x01, 0x00, 0x41, 0x00, 0x0d, 0x00, 0x0a, 0x00, 0x00, 0x00,
+            // "Bc\r\n", padded to 32 bits
+            0x10, 0x00, 0x01, 0x00, 0x42, 0x00, 0x63, 0x00, 0x0d, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // "x"
+            0x08, 0x00, 0x01, 0x00, 0x78, 0x00, 0x00, 0x00,
+        ];
+        const GERMAN: [u8; 28] = [
+            0x01, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00,
+            // "D\r\n"
+            0x0c, 0x00, 0x01, 0x00, 0x44, 0x00, 0x0d, 0x00, 0x0a, 0x00, 0x00, 0x00,
+        ];
+        let mut messages = MessageTable::new();
+        messages.add("FIRST", 1, "A\r\n")
+                .add("SECOND", 2, "Bc\r\n")
+                .add("FAILED", MessageTable::message_id(MessageSeverity::Error, 0, 5), "x")
+                .translate(0x0407, 1, "D\r\n");
+        let resources = messages.resources(0x0409);
+        assert_eq!(resources.keys().cloned().collect::<Vec<_>>(), vec![0x0407, 0x0409]);
+        assert_eq!(&resources[&0x0409][..], &ENGLISH[..]);
+        assert_eq!(&resources[&0x0407][..], &GERMAN[..]);
+    }
+}
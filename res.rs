@@ -0,0 +1,376 @@
+//! Writer for binary resource (`.res`) files
+//!
+//! This is the 32-bit resource file format, as written by `rc.exe` and `windres.exe`.
+//! It is a simple list of resource entries, each consisting of a header that holds
+//! type, name and language, followed by the raw resource data.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+/// Resource types defined by Windows, only those we actually write
+pub const RT_CURSOR: u16 = 1;
+pub const RT_BITMAP: u16 = 2;
+pub const RT_ICON: u16 = 3;
+pub const RT_MENU: u16 = 4;
+pub const RT_DIALOG: u16 = 5;
+pub const RT_STRING: u16 = 6;
+pub const RT_ACCELERATOR: u16 = 9;
+pub const RT_RCDATA: u16 = 10;
+pub const RT_MESSAGETABLE: u16 = 11;
+pub const RT_GROUP_CURSOR: u16 = 12;
+pub const RT_GROUP_ICON: u16 = 14;
+pub const RT_VERSION: u16 = 16;
+pub const RT_ANICURSOR: u16 = 21;
+pub const RT_ANIICON: u16 = 22;
+pub const RT_MANIFEST: u16 = 24;
+
+/// The name of a resource, either a number or a string
+///
+/// Names are case insensitive, the resource compilers store them in uppercase.
+/// In the resource directory of an executable, named resources come before
+/// numbered ones, both in ascending order.
+///
+/// ```rust
+/// use winres::ResourceId;
+///
+/// assert_eq!(ResourceId::from(2), ResourceId::Ordinal(2));
+/// assert_eq!(ResourceId::from("tray"), ResourceId::Name("TRAY".to_string()));
+/// assert!(ResourceId::from("TRAY") < ResourceId::from(1));
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub enum ResourceId {
+    Ordinal(u16),
+    /// always uppercase, when created with `from()`
+    Name(String),
+}
+
+impl ResourceId {
+    /// Check that the id can be written to a resource script
+    pub(crate) fn check(&self) -> Result<(), String> {
+        match *self {
+            ResourceId::Ordinal(0) => Err("0 is not a valid resource id".to_string()),
+            ResourceId::Ordinal(_) => Ok(()),
+            ResourceId::Name(ref name) => {
+                if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) ||
+                   name.contains(|c: char| c.is_whitespace() || c == '"' || c == ',') {
+                    Err(format!("{:?} is not a valid resource name", name))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+impl From<u16> for ResourceId {
+    fn from(id: u16) -> ResourceId {
+        ResourceId::Ordinal(id)
+    }
+}
+
+impl<'a> From<&'a str> for ResourceId {
+    fn from(name: &'a str) -> ResourceId {
+        ResourceId::Name(name.to_uppercase())
+    }
+}
+
+impl From<String> for ResourceId {
+    fn from(name: String) -> ResourceId {
+        ResourceId::from(name.as_str())
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResourceId::Ordinal(n) => write!(f, "{}", n),
+            ResourceId::Name(ref name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// The contents of a custom resource, a file or the bytes themselves
+///
+/// ```rust
+/// use winres::ResourceData;
+///
+/// assert_eq!(ResourceData::from("data/defaults.toml"), ResourceData::File("data/defaults.toml".to_string()));
+/// assert_eq!(ResourceData::from(&b"\x01\x02"[..]), ResourceData::Bytes(vec![1, 2]));
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ResourceData {
+    /// a path, relative paths are relative to the directory of `Cargo.toml`
+    File(String),
+    Bytes(Vec<u8>),
+}
+
+impl<'a> From<&'a str> for ResourceData {
+    fn from(path: &'a str) -> ResourceData {
+        ResourceData::File(path.to_string())
+    }
+}
+
+impl From<String> for ResourceData {
+    fn from(path: String) -> ResourceData {
+        ResourceData::File(path)
+    }
+}
+
+impl<'a> From<&'a [u8]> for ResourceData {
+    fn from(bytes: &'a [u8]) -> ResourceData {
+        ResourceData::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for ResourceData {
+    fn from(bytes: Vec<u8>) -> ResourceData {
+        ResourceData::Bytes(bytes)
+    }
+}
+
+impl Ord for ResourceId {
+    fn cmp(&self, other: &ResourceId) -> Ordering {
+        match (self, other) {
+            (ResourceId::Ordinal(a), ResourceId::Ordinal(b)) => a.cmp(b),
+            (ResourceId::Name(a), ResourceId::Name(b)) => a.encode_utf16().cmp(b.encode_utf16()),
+            (ResourceId::Name(_), ResourceId::Ordinal(_)) => Ordering::Less,
+            (ResourceId::Ordinal(_), ResourceId::Name(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for ResourceId {
+    fn partial_cmp(&self, other: &ResourceId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A single resource entry, with the data already serialized
+pub struct Resource {
+    pub res_type: ResourceId,
+    pub name: ResourceId,
+    pub language: u16,
+    pub memory_flags: u16,
+    pub data: Vec<u8>,
+}
+
+impl Resource {
+    pub fn new(res_type: ResourceId, name: ResourceId, language: u16, data: Vec<u8>) -> Resource {
+        Resource {
+            res_type,
+            name,
+            language,
+            // MOVEABLE | PURE
+            memory_flags: 0x0030,
+            data,
+        }
+    }
+}
+
+/// Write all resources to a `.res` file
+pub fn write_res<W: Write>(w: &mut W, resources: &[Resource]) -> io::Result<()> {
+    // every resource file starts with an empty entry, so that the format
+    // can be distinguished from 16-bit resource files
+    w.write_all(&header(0, &ResourceId::Ordinal(0), &ResourceId::Ordinal(0), 0, 0))?;
+    for r in resources {
+        w.write_all(&header(r.data.len() as u32, &r.res_type, &r.name, r.memory_flags, r.language))?;
+        w.write_all(&r.data)?;
+        w.write_all(&[0; 3][..padding(r.data.len())])?;
+    }
+    Ok(())
+}
+
+fn header(data_size: u32, res_type: &ResourceId, name: &ResourceId, memory_flags: u16, language: u16) -> Vec<u8> {
+    let mut h = Vec::with_capacity(32);
+    push_u32(&mut h, data_size);
+    // the header size is filled in below
+    push_u32(&mut h, 0);
+    push_id(&mut h, res_type);
+    push_id(&mut h, name);
+    align(&mut h);
+    push_u32(&mut h, 0); // DataVersion
+    push_u16(&mut h, memory_flags);
+    push_u16(&mut h, language);
+    push_u32(&mut h, 0); // Version
+    push_u32(&mut h, 0); // Characteristics
+    let size = h.len() as u32;
+    h[4..8].copy_from_slice(&size.to_le_bytes());
+    h
+}
+
+fn push_id(buf: &mut Vec<u8>, id: &ResourceId) {
+    match *id {
+        ResourceId::Ordinal(n) => {
+            push_u16(buf, 0xFFFF);
+            push_u16(buf, n);
+        }
+        ResourceId::Name(ref name) => push_wstr(buf, name),
+    }
+}
+
+/// Build a `VS_VERSIONINFO` style node
+///
+/// All nodes of the version info tree share the same layout: a length, the length of the
+/// value, a type flag (`true` for text values), a key and the value followed by the
+/// child nodes, every part aligned to 32 bits.
+pub fn version_node(key: &str, text: bool, value: &[u8], value_length: u16, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_u16(&mut buf, 0);
+    push_u16(&mut buf, value_length);
+    push_u16(&mut buf, if text { 1 } else { 0 });
+    push_wstr(&mut buf, key);
+    align(&mut buf);
+    buf.extend_from_slice(value);
+    for child in children {
+        align(&mut buf);
+        buf.extend_from_slice(child);
+    }
+    let length = buf.len() as u16;
+    buf[0..2].copy_from_slice(&length.to_le_bytes());
+    buf
+}
+
+/// Group a string table into `RT_STRING` resources of 16 strings each
+///
+/// The strings `16 * (n - 1)` to `16 * n - 1` form the resource with the id `n`. Every
+/// string is stored with its length and without terminating zero, missing strings are empty.
+pub fn string_resources(strings: &BTreeMap<u16, String>, language: u16) -> Vec<Resource> {
+    let mut bundles: BTreeMap<u16, [&str; 16]> = BTreeMap::new();
+    for (&id, text) in strings {
+        bundles.entry(id / 16).or_insert([""; 16])[id as usize % 16] = text;
+    }
+    bundles.into_iter()
+        .map(|(bundle, texts)| {
+            let mut data = Vec::new();
+            for text in texts.iter() {
+                let chars: Vec<u16> = text.encode_utf16().collect();
+                push_u16(&mut data, chars.len() as u16);
+                for c in chars {
+                    push_u16(&mut data, c);
+                }
+            }
+            let mut r = Resource::new(ResourceId::Ordinal(RT_STRING), ResourceId::Ordinal(bundle + 1), language, data);
+            // MOVEABLE | PURE | DISCARDABLE
+            r.memory_flags = 0x1030;
+            r
+        })
+        .collect()
+}
+
+/// Number of bytes needed to align `len` to 32 bits
+pub fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+/// Number of bytes needed to align `len` to 64 bits
+pub fn padding8(len: usize) -> usize {
+    (8 - len % 8) % 8
+}
+
+pub fn align(buf: &mut Vec<u8>) {
+    let pad = padding(buf.len());
+    buf.extend_from_slice(&[0; 3][..pad]);
+}
+
+pub fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append a zero terminated UTF-16 string
+pub fn push_wstr(buf: &mut Vec<u8>, s: &str) {
+    for c in s.encode_utf16() {
+        push_u16(buf, c);
+    }
+    push_u16(buf, 0);
+}
+
+/// Encode a zero terminated UTF-16 string
+pub fn wstr(s: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_wstr(&mut buf, s);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_info() {
+        // 1 VERSIONINFO with FILEVERSION 1,2,3,4, PRODUCTVERSION 1,2,0,0, FILEFLAGSMASK 0x3f,
+        // FILEOS 0x40004, FILETYPE 1, a "ProductName" of "Test" and its translation, as
+        // compiled by llvm-rc with LANGUAGE 0x9, 0x1
+        const EXPECTED: [u8; 328] = [
+            0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x08, 0x01, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0xff, 0xff, 0x10, 0x00, 0xff, 0xff, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x09, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x08, 0x01, 0x34, 0x00, 0x00, 0x00, 0x56, 0x00, 0x53, 0x00, 0x5f, 0x00, 0x56, 0x00, 0x45, 0x00,
+            0x52, 0x00, 0x53, 0x00, 0x49, 0x00, 0x4f, 0x00, 0x4e, 0x00, 0x5f, 0x00, 0x49, 0x00, 0x4e, 0x00,
+            0x46, 0x00, 0x4f, 0x00, 0x00, 0x00, 0x00, 0x00, 0xbd, 0x04, 0xef, 0xfe, 0x00, 0x00, 0x01, 0x00,
+            0x02, 0x00, 0x01, 0x00, 0x04, 0x00, 0x03, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x00, 0x00, 0x00,
+            0x01, 0x00, 0x53, 0x00, 0x74, 0x00, 0x72, 0x00, 0x69, 0x00, 0x6e, 0x00, 0x67, 0x00, 0x46, 0x00,
+            0x69, 0x00, 0x6c, 0x00, 0x65, 0x00, 0x49, 0x00, 0x6e, 0x00, 0x66, 0x00, 0x6f, 0x00, 0x00, 0x00,
+            0x42, 0x00, 0x00, 0x00, 0x01, 0x00, 0x30, 0x00, 0x34, 0x00, 0x30, 0x00, 0x39, 0x00, 0x30, 0x00,
+            0x34, 0x00, 0x62, 0x00, 0x30, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x05, 0x00, 0x01, 0x00, 0x50, 0x00,
+            0x72, 0x00, 0x6f, 0x00, 0x64, 0x00, 0x75, 0x00, 0x63, 0x00, 0x74, 0x00, 0x4e, 0x00, 0x61, 0x00,
+            0x6d, 0x00, 0x65, 0x00, 0x00, 0x00, 0x00, 0x00, 0x54, 0x00, 0x65, 0x00, 0x73, 0x00, 0x74, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x44, 0x00, 0x00, 0x00, 0x01, 0x00, 0x56, 0x00, 0x61, 0x00, 0x72, 0x00,
+            0x46, 0x00, 0x69, 0x00, 0x6c, 0x00, 0x65, 0x00, 0x49, 0x00, 0x6e, 0x00, 0x66, 0x00, 0x6f, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x24, 0x00, 0x04, 0x00, 0x00, 0x00, 0x54, 0x00, 0x72, 0x00, 0x61, 0x00,
+            0x6e, 0x00, 0x73, 0x00, 0x6c, 0x00, 0x61, 0x00, 0x74, 0x00, 0x69, 0x00, 0x6f, 0x00, 0x6e, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x09, 0x04, 0xb0, 0x04,
+        ];
+        let mut fixed = Vec::new();
+        for v in &[0xFEEF04BD, 0x00010000, 0x00010002, 0x00030004, 0x00010002, 0, 0x3F, 0, 0x40004, 1, 0, 0, 0] {
+            push_u32(&mut fixed, *v);
+        }
+        let value = wstr("Test");
+        let name = version_node("ProductName", true, &value, (value.len() / 2) as u16, &[]);
+        let table = version_node("040904b0", true, &[], 0, &[name]);
+        let string_info = version_node("StringFileInfo", true, &[], 0, &[table]);
+        let translation = [0x09, 0x04, 0xB0, 0x04];
+        let var = version_node("Translation", false, &translation, translation.len() as u16, &[]);
+        let var_info = version_node("VarFileInfo", true, &[], 0, &[var]);
+        let data = version_node("VS_VERSION_INFO", false, &fixed, fixed.len() as u16, &[string_info, var_info]);
+
+        let mut buf = Vec::new();
+        write_res(&mut buf, &[Resource::new(ResourceId::Ordinal(RT_VERSION), ResourceId::Ordinal(1), 0x409, data)]).unwrap();
+        assert_eq!(&buf[..], &EXPECTED[..]);
+    }
+
+    #[test]
+    fn string_table() {
+        // STRINGTABLE { 1, "One" 3, "Drei ä" 17, "x" }, as compiled by llvm-rc with
+        // LANGUAGE 0x7, 0x1, two blocks of 16 strings
+        const EXPECTED: [u8; 184] = [
+            0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x32, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0xff, 0xff, 0x06, 0x00, 0xff, 0xff, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x30, 0x10, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x4f, 0x00, 0x6e, 0x00, 0x65, 0x00, 0x00, 0x00, 0x06, 0x00, 0x44, 0x00,
+            0x72, 0x00, 0x65, 0x00, 0x69, 0x00, 0x20, 0x00, 0xe4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0xff, 0xff, 0x06, 0x00,
+            0xff, 0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x10, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let strings: BTreeMap<u16, String> = vec![(1, "One"), (3, "Drei ä"), (17, "x")].into_iter()
+            .map(|(id, text)| (id, text.to_string()))
+            .collect();
+        let mut buf = Vec::new();
+        write_res(&mut buf, &string_resources(&strings, 0x407)).unwrap();
+        assert_eq!(&buf[..], &EXPECTED[..]);
+    }
+}
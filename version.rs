@@ -0,0 +1,429 @@
+//! Typed values for the fixed part of the version info
+//!
+//! These are the `VS_FF_*`, `VOS_*`, `VFT_*` and `VFT2_*` constants from `winver.h`.
+
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Attributes of the file, the `FILEFLAGS` field
+///
+/// ```rust
+/// use winres::FileFlags;
+///
+/// let flags = FileFlags::DEBUG | FileFlags::PRERELEASE;
+/// assert_eq!(flags.bits(), 0x3);
+/// assert!(flags.contains(FileFlags::DEBUG));
+/// assert!(!flags.contains(FileFlags::PATCHED));
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct FileFlags {
+    bits: u32,
+}
+
+impl FileFlags {
+    /// The file contains debugging information
+    pub const DEBUG: FileFlags = FileFlags { bits: 0x01 };
+    /// The file is a development version, not a commercially released product
+    pub const PRERELEASE: FileFlags = FileFlags { bits: 0x02 };
+    /// The file has been modified and is not identical to the original shipping file
+    pub const PATCHED: FileFlags = FileFlags { bits: 0x04 };
+    /// The file was not built using standard release procedures,
+    /// the `"PrivateBuild"` string has to be set
+    pub const PRIVATEBUILD: FileFlags = FileFlags { bits: 0x08 };
+    /// The version info was created dynamically
+    pub const INFOINFERRED: FileFlags = FileFlags { bits: 0x10 };
+    /// The file is a variation of the normal file of the same version,
+    /// the `"SpecialBuild"` string has to be set
+    pub const SPECIALBUILD: FileFlags = FileFlags { bits: 0x20 };
+
+    /// No flags set
+    pub fn empty() -> FileFlags {
+        FileFlags { bits: 0 }
+    }
+
+    /// All flags set, this is the value of `FILEFLAGSMASK`
+    pub fn all() -> FileFlags {
+        FileFlags { bits: 0x3F }
+    }
+
+    /// Convert from the raw value, `None` if unknown bits are set
+    pub fn from_bits(bits: u32) -> Option<FileFlags> {
+        if bits & !FileFlags::all().bits == 0 {
+            Some(FileFlags { bits })
+        } else {
+            None
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(self, other: FileFlags) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    /// Get a single flag by its lowercase name, e.g., `"prerelease"`
+    pub(crate) fn from_name(name: &str) -> Option<FileFlags> {
+        FLAG_NAMES.iter().find(|n| n.1.eq_ignore_ascii_case(name)).map(|n| n.0)
+    }
+}
+
+const FLAG_NAMES: [(FileFlags, &str); 6] = [(FileFlags::DEBUG, "DEBUG"),
+                                            (FileFlags::PRERELEASE, "PRERELEASE"),
+                                            (FileFlags::PATCHED, "PATCHED"),
+                                            (FileFlags::PRIVATEBUILD, "PRIVATEBUILD"),
+                                            (FileFlags::INFOINFERRED, "INFOINFERRED"),
+                                            (FileFlags::SPECIALBUILD, "SPECIALBUILD")];
+
+impl BitOr for FileFlags {
+    type Output = FileFlags;
+
+    fn bitor(self, other: FileFlags) -> FileFlags {
+        FileFlags { bits: self.bits | other.bits }
+    }
+}
+
+impl BitOrAssign for FileFlags {
+    fn bitor_assign(&mut self, other: FileFlags) {
+        self.bits |= other.bits;
+    }
+}
+
+impl BitAnd for FileFlags {
+    type Output = FileFlags;
+
+    fn bitand(self, other: FileFlags) -> FileFlags {
+        FileFlags { bits: self.bits & other.bits }
+    }
+}
+
+impl fmt::Debug for FileFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let set: Vec<&str> = FLAG_NAMES.iter().filter(|n| self.contains(n.0)).map(|n| n.1).collect();
+        if set.is_empty() {
+            write!(f, "(empty)")
+        } else {
+            write!(f, "{}", set.join(" | "))
+        }
+    }
+}
+
+/// The operating system the file was designed for, the `FILEOS` field
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum FileOs {
+    UNKNOWN,
+    /// MS-DOS
+    DOS,
+    /// Windows NT
+    NT,
+    /// 16-bit Windows
+    WINDOWS16,
+    /// 32-bit Windows
+    WINDOWS32,
+    /// 16-bit Windows running on MS-DOS
+    DOS_WINDOWS16,
+    /// 32-bit Windows running on MS-DOS
+    DOS_WINDOWS32,
+    /// Windows NT, this is what any Rust compiler output should use
+    NT_WINDOWS32,
+}
+
+impl FileOs {
+    pub fn value(self) -> u32 {
+        match self {
+            FileOs::UNKNOWN => 0x00000,
+            FileOs::DOS => 0x10000,
+            FileOs::NT => 0x40000,
+            FileOs::WINDOWS16 => 0x00001,
+            FileOs::WINDOWS32 => 0x00004,
+            FileOs::DOS_WINDOWS16 => 0x10001,
+            FileOs::DOS_WINDOWS32 => 0x10004,
+            FileOs::NT_WINDOWS32 => 0x40004,
+        }
+    }
+
+    /// Get a value by its name in lowercase with dashes, e.g., `"nt-windows32"`
+    pub(crate) fn from_name(name: &str) -> Option<FileOs> {
+        [FileOs::UNKNOWN, FileOs::DOS, FileOs::NT, FileOs::WINDOWS16, FileOs::WINDOWS32,
+         FileOs::DOS_WINDOWS16, FileOs::DOS_WINDOWS32, FileOs::NT_WINDOWS32]
+            .iter().cloned().find(|v| matches_name(v, name))
+    }
+}
+
+/// The general type of the file, the `FILETYPE` field
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum FileType {
+    UNKNOWN,
+    /// An application, i.e., an `.exe`
+    APP,
+    /// A dynamic link library
+    DLL,
+    /// A device driver, the subtype has to be one of the `DRV_*` values of [`FileSubtype`]
+    ///
+    /// [`FileSubtype`]: enum.FileSubtype.html
+    DRV,
+    /// A font, the subtype has to be one of the `FONT_*` values of [`FileSubtype`]
+    ///
+    /// [`FileSubtype`]: enum.FileSubtype.html
+    FONT,
+    /// A virtual device, the subtype is its identifier, see [`FileSubtype::VXD`]
+    ///
+    /// [`FileSubtype::VXD`]: enum.FileSubtype.html#variant.VXD
+    VXD,
+    /// A static link library
+    STATIC_LIB,
+}
+
+impl FileType {
+    pub fn value(self) -> u32 {
+        match self {
+            FileType::UNKNOWN => 0,
+            FileType::APP => 1,
+            FileType::DLL => 2,
+            FileType::DRV => 3,
+            FileType::FONT => 4,
+            FileType::VXD => 5,
+            FileType::STATIC_LIB => 7,
+        }
+    }
+
+    /// Get a value by its name in lowercase with dashes, e.g., `"static-lib"`
+    pub(crate) fn from_name(name: &str) -> Option<FileType> {
+        [FileType::UNKNOWN, FileType::APP, FileType::DLL, FileType::DRV, FileType::FONT,
+         FileType::VXD, FileType::STATIC_LIB]
+            .iter().cloned().find(|v| matches_name(v, name))
+    }
+}
+
+/// The function of the file, the `FILESUBTYPE` field
+///
+/// Only drivers, fonts and virtual devices have a subtype.
+#[allow(non_camel_case_types)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum FileSubtype {
+    UNKNOWN,
+    DRV_PRINTER,
+    DRV_KEYBOARD,
+    DRV_LANGUAGE,
+    DRV_DISPLAY,
+    DRV_MOUSE,
+    DRV_NETWORK,
+    DRV_SYSTEM,
+    DRV_INSTALLABLE,
+    DRV_SOUND,
+    DRV_COMM,
+    DRV_INPUTMETHOD,
+    DRV_VERSIONED_PRINTER,
+    FONT_RASTER,
+    FONT_VECTOR,
+    FONT_TRUETYPE,
+    /// The virtual device identifier of a [`FileType::VXD`]
+    ///
+    /// [`FileType::VXD`]: enum.FileType.html#variant.VXD
+    VXD(u32),
+}
+
+impl FileSubtype {
+    pub fn value(self) -> u32 {
+        match self {
+            FileSubtype::UNKNOWN => 0,
+            FileSubtype::DRV_PRINTER => 1,
+            FileSubtype::DRV_KEYBOARD => 2,
+            FileSubtype::DRV_LANGUAGE => 3,
+            FileSubtype::DRV_DISPLAY => 4,
+            FileSubtype::DRV_MOUSE => 5,
+            FileSubtype::DRV_NETWORK => 6,
+            FileSubtype::DRV_SYSTEM => 7,
+            FileSubtype::DRV_INSTALLABLE => 8,
+            FileSubtype::DRV_SOUND => 9,
+            FileSubtype::DRV_COMM => 10,
+            FileSubtype::DRV_INPUTMETHOD => 11,
+            FileSubtype::DRV_VERSIONED_PRINTER => 12,
+            FileSubtype::FONT_RASTER => 1,
+            FileSubtype::FONT_VECTOR => 2,
+            FileSubtype::FONT_TRUETYPE => 3,
+            FileSubtype::VXD(id) => id,
+        }
+    }
+
+    /// The file type this subtype belongs to, `None` for `UNKNOWN`, which fits all types
+    pub fn file_type(self) -> Option<FileType> {
+        match self {
+            FileSubtype::UNKNOWN => None,
+            FileSubtype::FONT_RASTER | FileSubtype::FONT_VECTOR | FileSubtype::FONT_TRUETYPE => Some(FileType::FONT),
+            FileSubtype::VXD(_) => Some(FileType::VXD),
+            _ => Some(FileType::DRV),
+        }
+    }
+
+    /// Get a value by its name in lowercase with dashes, e.g., `"font-truetype"`
+    ///
+    /// The identifier of a virtual device can not be named, it has to be given as number.
+    pub(crate) fn from_name(name: &str) -> Option<FileSubtype> {
+        [FileSubtype::UNKNOWN, FileSubtype::DRV_PRINTER, FileSubtype::DRV_KEYBOARD,
+         FileSubtype::DRV_LANGUAGE, FileSubtype::DRV_DISPLAY, FileSubtype::DRV_MOUSE,
+         FileSubtype::DRV_NETWORK, FileSubtype::DRV_SYSTEM, FileSubtype::DRV_INSTALLABLE,
+         FileSubtype::DRV_SOUND, FileSubtype::DRV_COMM, FileSubtype::DRV_INPUTMETHOD,
+         FileSubtype::DRV_VERSIONED_PRINTER, FileSubtype::FONT_RASTER, FileSubtype::FONT_VECTOR,
+         FileSubtype::FONT_TRUETYPE]
+            .iter().cloned().find(|v| matches_name(v, name))
+    }
+}
+
+/// Compare the `Debug` name of a value, e.g., `STATIC_LIB`, with `static-lib`
+fn matches_name<T: fmt::Debug>(value: &T, name: &str) -> bool {
+    format!("{:?}", value).replace('_', "-").eq_ignore_ascii_case(name)
+}
+
+/// Check that the raw values of the fixed version info fit together
+///
+/// Returns a description of the first problem found.
+pub fn check(file_type: u64, file_subtype: u64, flags: u64, flags_mask: u64) -> Result<(), String> {
+    if flags & !flags_mask != 0 {
+        return Err(format!("FILEFLAGS {:#x} has bits set that are not in FILEFLAGSMASK {:#x}", flags, flags_mask));
+    }
+    if flags & !(FileFlags::all().bits() as u64) != 0 {
+        return Err(format!("FILEFLAGS {:#x} has unknown bits set", flags));
+    }
+    let max_subtype = match file_type {
+        3 => 12,
+        4 => 3,
+        5 => u32::MAX as u64,
+        0..=2 | 7 => 0,
+        _ => return Err(format!("FILETYPE {:#x} is unknown", file_type)),
+    };
+    if file_subtype > max_subtype {
+        return Err(format!("FILESUBTYPE {:#x} is not valid for FILETYPE {:#x}", file_subtype, file_type));
+    }
+    Ok(())
+}
+
+/// Convert a version with up to four components, e.g., `"1.2.3.4"`, to a 64-bit value
+///
+/// Missing components are `0`.
+pub fn parse_numeric_version(version: &str) -> Result<u64, String> {
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() > 4 {
+        return Err(format!("{:?} has more than four components", version));
+    }
+    let mut value = 0;
+    for i in 0..4 {
+        let part = match parts.get(i) {
+            Some(p) => p.parse::<u64>().map_err(|_| format!("{:?} is not a number", p))?,
+            None => 0,
+        };
+        if part > 0xFFFF {
+            return Err(format!("component {} is larger than 65535", part));
+        }
+        value = value << 16 | part;
+    }
+    Ok(value)
+}
+
+/// How the fourth component of `FILEVERSION` and `PRODUCTVERSION` is derived from the
+/// version in `Cargo.toml`
+///
+/// The version info only has room for four 16-bit numbers, while a semantic version has
+/// three numbers followed by an optional pre-release and build metadata.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum VersionStrategy {
+    /// The fourth component is always `0`, `1.2.3-rc.4+5` becomes `1.2.3.0`
+    Zero,
+    /// The last number of the pre-release, `1.2.3-rc.4` becomes `1.2.3.4`
+    ///
+    /// Releases and pre-releases without a number get `0`.
+    PreReleaseNumber,
+    /// The pre-release channel and number, so that versions sort like semver does
+    ///
+    /// `alpha.N` becomes `10000 + N`, `beta.N` becomes `20000 + N`, `rc.N` becomes
+    /// `30000 + N` and a release becomes `65535`. Other pre-releases and numbers above
+    /// `9999` are rejected.
+    PreReleaseChannel,
+    /// The last number of the build metadata, `1.2.3+build.42` becomes `1.2.3.42`
+    ///
+    /// Versions without a number in the build metadata get `0`.
+    BuildNumber,
+}
+
+// `#[default]` on the variant needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for VersionStrategy {
+    fn default() -> VersionStrategy {
+        VersionStrategy::Zero
+    }
+}
+
+impl VersionStrategy {
+    /// Get a value by its name in lowercase with dashes, e.g., `"pre-release-number"`
+    pub(crate) fn from_name(name: &str) -> Option<VersionStrategy> {
+        match name {
+            "zero" => Some(VersionStrategy::Zero),
+            "pre-release-number" => Some(VersionStrategy::PreReleaseNumber),
+            "pre-release-channel" => Some(VersionStrategy::PreReleaseChannel),
+            "build-number" => Some(VersionStrategy::BuildNumber),
+            _ => None,
+        }
+    }
+}
+
+/// Convert a semantic version to the 64-bit value of the version info
+pub fn parse_version(version: &str, strategy: VersionStrategy) -> Result<u64, String> {
+    let (version, build) = match version.find('+') {
+        Some(i) => (&version[..i], &version[i + 1..]),
+        None => (version, ""),
+    };
+    let (version, pre) = match version.find('-') {
+        Some(i) => (&version[..i], &version[i + 1..]),
+        None => (version, ""),
+    };
+    let mut parts = Vec::new();
+    for part in version.split('.') {
+        parts.push(part.parse::<u64>().map_err(|_| format!("{:?} is not a number", part))?);
+    }
+    if parts.len() != 3 {
+        return Err(format!("{:?} does not have three components", version));
+    }
+    let fourth = match strategy {
+        VersionStrategy::Zero => 0,
+        VersionStrategy::PreReleaseNumber => last_number(pre)?,
+        VersionStrategy::BuildNumber => last_number(build)?,
+        VersionStrategy::PreReleaseChannel if pre.is_empty() => 0xFFFF,
+        VersionStrategy::PreReleaseChannel => {
+            let base = match pre.split('.').next().unwrap_or("").to_ascii_lowercase().as_str() {
+                "alpha" => 10000,
+                "beta" => 20000,
+                "rc" => 30000,
+                _ => return Err(format!("pre-release {:?} is not alpha, beta or rc", pre)),
+            };
+            let n = last_number(pre)?;
+            if n > 9999 {
+                return Err(format!("pre-release number {} is larger than 9999", n));
+            }
+            base + n
+        }
+    };
+    parts.push(fourth);
+    let mut value = 0;
+    for part in parts {
+        if part > 0xFFFF {
+            return Err(format!("component {} is larger than 65535", part));
+        }
+        value = value << 16 | part;
+    }
+    Ok(value)
+}
+
+/// The last numeric identifier of a pre-release or build metadata
+fn last_number(s: &str) -> Result<u64, String> {
+    match s.split('.').rev().find(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())) {
+        Some(id) => id.parse().map_err(|_| format!("{} is larger than 65535", id)),
+        None => Ok(0),
+    }
+}